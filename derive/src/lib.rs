@@ -1,8 +1,8 @@
 use proc_macro::TokenStream;
 use quote::format_ident;
-use syn::{parse_quote, spanned::Spanned};
+use syn::{parse::{Parse, ParseStream}, parse_quote, punctuated::Punctuated, spanned::Spanned, Token};
 
-#[proc_macro_derive(RealQuickSer)]
+#[proc_macro_derive(RealQuickSer, attributes(quick_ser))]
 pub fn derive_quick_ser(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
 
@@ -11,7 +11,7 @@ pub fn derive_quick_ser(input: TokenStream) -> TokenStream {
     let mut generics = input.generics.clone();
 
     for param in generics.type_params_mut() {
-        param.bounds.push(parse_quote!(RealQuickSer));
+        param.bounds.push(parse_quote!(::quick_proc::__private::RealQuickSer));
     }
 
     let mut type_params = input.generics.clone();
@@ -19,12 +19,232 @@ pub fn derive_quick_ser(input: TokenStream) -> TokenStream {
         param.bounds.clear();
     }
 
+    let args = quick_ser_args(&input.attrs, &["allow_padding"]);
+    let allow_padding = has_arg(&args, "allow_padding");
+
+    let is_valid = match &input.data {
+        syn::Data::Struct(s) => {
+            let checks = s.fields.iter().enumerate().map(|(i, f)| {
+                let ty = &f.ty;
+                let field = match &f.ident {
+                    Some(ident) => quote::quote!(#ident),
+                    None => {
+                        let index = syn::Index::from(i);
+                        quote::quote!(#index)
+                    },
+                };
+
+                quote::quote_spanned! {ty.span()=>
+                    <#ty as ::quick_proc::__private::RealQuickSer>::is_valid(
+                        &bytes[std::mem::offset_of!(Self, #field)..][..std::mem::size_of::<#ty>()]
+                    )
+                }
+            });
+
+            quote::quote! {
+                true #( && #checks )*
+            }
+        },
+        syn::Data::Enum(e) => {
+            if e.variants.iter().any(|v| !v.fields.is_empty()) {
+                panic!("RealQuickSer can only be derived for fieldless enums");
+            }
+            if e.variants.is_empty() {
+                panic!("RealQuickSer cannot be derived for enums without variants");
+            }
+
+            let variants = e.variants.iter().map(|v| &v.ident);
+
+            quote::quote! {
+                [#( Self::#variants ),*].iter().any(|variant| bytes == unsafe {
+                    std::slice::from_raw_parts(
+                        variant as *const Self as *const u8, 
+                        std::mem::size_of::<Self>()
+                    )
+                })
+            }
+        },
+        syn::Data::Union(_) => panic!("union is not supported"),
+    };
+
+    let (ser_portable, de_ser_portable) = match &input.data {
+        syn::Data::Struct(s) => {
+            let is_tuple = s.fields.iter().next().map(|f| f.ident.is_none()).unwrap_or(false);
+            let fields = s.fields.iter().enumerate().map(|(i, f)| match &f.ident {
+                Some(ident) => quote::quote!(#ident),
+                None => {
+                    let index = syn::Index::from(i);
+                    quote::quote!(#index)
+                },
+            });
+            let calls = s.fields.iter().map(|f| {
+                quote::quote_spanned!(f.ty.span() =>
+                    ::quick_proc::__private::RealQuickSer::try_de_ser_portable(source)?
+                )
+            });
+
+            let ser = fields.clone().map(|field| quote::quote! {
+                ::quick_proc::__private::RealQuickSer::ser_portable(&self.#field, buffer);
+            });
+
+            let de_ser = if is_tuple {
+                quote::quote!(Ok(Self(#( #calls, )*)))
+            } else {
+                quote::quote!(Ok(Self {#( #fields: #calls, )*}))
+            };
+
+            (quote::quote!(#( #ser )*), de_ser)
+        },
+        syn::Data::Enum(e) => {
+            let variants = e.variants.iter().map(|v| &v.ident);
+            let variants2 = variants.clone();
+            let width = enum_width(&input.attrs);
+
+            (
+                quote::quote! {
+                    ::quick_proc::__private::QuickSink::write(buffer, &(*self as i128).to_le_bytes()[..#width]);
+                },
+                quote::quote! {
+                    let offset = ::quick_proc::__private::QuickSource::offset(source);
+                    let bytes = ::quick_proc::__private::QuickSource::take(source, #width)?;
+                    #(
+                        if bytes == &(Self::#variants as i128).to_le_bytes()[..#width] {
+                            return Ok(Self::#variants2);
+                        }
+                    )*
+                    Err(::quick_proc::__private::DeError::InvalidTag { offset })
+                },
+            )
+        },
+        syn::Data::Union(_) => panic!("union is not supported"),
+    };
+
+    let portable_layout = match &input.data {
+        syn::Data::Struct(s) if !allow_padding && has_stable_repr(&input.attrs) => {
+            let types = s.fields.iter().map(|f| &f.ty);
+
+            quote::quote! {
+                const PORTABLE_LAYOUT: bool = true #( && <#types as ::quick_proc::__private::RealQuickSer>::PORTABLE_LAYOUT )*;
+            }
+        },
+        syn::Data::Enum(_) => match enum_repr(&input.attrs) {
+            Some(repr) if repr != "usize" && repr != "isize" => quote::quote! {
+                const PORTABLE_LAYOUT: bool = std::mem::size_of::<Self>() == 1 || cfg!(target_endian = "little");
+            },
+            _ => quote::quote!(),
+        },
+        _ => quote::quote!(),
+    };
+
+    let layout_check = match &input.data {
+        syn::Data::Struct(s) if !allow_padding => {
+            let types = s.fields.iter().map(|f| &f.ty);
+            let types2 = types.clone();
+            let message = format!(
+                "`{}` contains padding bytes, reorder or pad the fields explicitly, \
+                or opt out with `#[quick_ser(allow_padding)]`", 
+                name,
+            );
+
+            quote::quote! {
+                const LAYOUT_CHECK: () = {
+                    #( let () = <#types as ::quick_proc::__private::RealQuickSer>::LAYOUT_CHECK; )*
+                    assert!(
+                        std::mem::size_of::<Self>() == 0 #( + std::mem::size_of::<#types2>() )*,
+                        #message
+                    );
+                };
+            }
+        },
+        _ => quote::quote!(),
+    };
+
+    let schema = match &input.data {
+        syn::Data::Struct(s) => {
+            let fields = s.fields.iter().enumerate()
+                .map(|(i, f)| format!("{}:{}", field_name(f, i), type_name(&f.ty)))
+                .collect::<Vec<_>>();
+            let types = s.fields.iter().map(|f| &f.ty);
+            let members = s.fields.iter().enumerate().map(|(i, f)| match &f.ident {
+                Some(ident) => quote::quote!(#ident),
+                None => {
+                    let index = syn::Index::from(i);
+                    quote::quote!(#index)
+                },
+            });
+
+            // the bulk copy follows the memory layout, which `repr(Rust)` may change
+            // without any change to the fields
+            schema_hash(format!("{}{{{}}}", name, fields.join(",")), quote::quote! {
+                #( let hash = ::quick_proc::__private::schema_combine(hash, <#types as ::quick_proc::__private::RealQuickSer>::QUICK_SCHEMA_HASH); )*
+                let hash = if !::quick_proc::__private::PORTABLE 
+                    || <Self as ::quick_proc::__private::RealQuickSer>::PORTABLE_LAYOUT 
+                {
+                    ::quick_proc::__private::schema_layout(
+                        hash, 
+                        std::mem::size_of::<Self>(), 
+                        &[#( std::mem::offset_of!(Self, #members) ),*],
+                    )
+                } else {
+                    hash
+                };
+            })
+        },
+        syn::Data::Enum(e) => {
+            let variants = e.variants.iter().map(|v| match &v.discriminant {
+                Some((_, d)) => format!("{}={}", v.ident, quote::quote!(#d)),
+                None => v.ident.to_string(),
+            }).collect::<Vec<_>>();
+            let repr = enum_repr(&input.attrs).map(|r| r.to_string()).unwrap_or_default();
+            let width = enum_width(&input.attrs);
+
+            schema_hash(format!("{}:{}{{{}}}", name, repr, variants.join(",")), quote::quote! {
+                let hash = ::quick_proc::__private::schema_layout(
+                    hash, 
+                    if ::quick_proc::__private::PORTABLE { #width } else { std::mem::size_of::<Self>() }, 
+                    &[],
+                );
+            })
+        },
+        syn::Data::Union(_) => panic!("union is not supported"),
+    };
+
+    let eager_check = if input.generics.params.is_empty() {
+        quote::quote! {
+            const _: () = <#name as ::quick_proc::__private::RealQuickSer>::LAYOUT_CHECK;
+        }
+    } else {
+        quote::quote!()
+    };
+
     quote::quote! {
-        impl #generics RealQuickSer for #name #type_params {}
+        impl #generics ::quick_proc::__private::RealQuickSer for #name #type_params {
+            #layout_check
+            #portable_layout
+            const QUICK_SCHEMA_HASH: u64 = #schema;
+
+            fn is_valid(bytes: &[u8]) -> bool {
+                #is_valid
+            }
+
+            fn ser_portable<__S: ::quick_proc::__private::QuickSink>(&self, buffer: &mut __S) {
+                #ser_portable
+            }
+
+            fn try_de_ser_portable<__S: ::quick_proc::__private::QuickSource>(source: &mut __S) -> Result<Self, ::quick_proc::__private::DeError> {
+                #de_ser_portable
+            }
+        }
+
+        impl #generics ::quick_proc::__private::QuickSer for #name #type_params {
+            ::quick_proc::__private::gen_quick_copy!();
+        }
+
+        #eager_check
     }.into()
 }
 
-#[proc_macro_derive(QuickSer)]
+#[proc_macro_derive(QuickSer, attributes(quick_ser, default))]
 pub fn derive_ser(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as syn::DeriveInput);
 
@@ -33,7 +253,7 @@ pub fn derive_ser(input: TokenStream) -> TokenStream {
     let mut generics = input.generics.clone();
 
     for param in generics.type_params_mut() {
-        param.bounds.push(parse_quote!(QuickSer));
+        param.bounds.push(parse_quote!(::quick_proc::__private::QuickSer));
     }
 
     let mut type_params = input.generics.clone();
@@ -43,173 +263,538 @@ pub fn derive_ser(input: TokenStream) -> TokenStream {
 
     let ser = match &input.data {
         syn::Data::Struct(s) => {
-            let is_tuple = s.fields.iter().next().map(|f| f.ident.is_none()).unwrap_or(false);
-            if is_tuple {
-                let names = (0..s.fields.len()).map(syn::Index::from);
+            let version = struct_version(&input.attrs);
+            let version_ser = version.map(|version| quote::quote! {
+                ::quick_proc::__private::QuickVarint::ser_varint(&#version, buffer);
+            });
+            let version_len = version.map(|version| quote::quote! {
+                + ::quick_proc::__private::QuickVarint::varint_len(&#version)
+            });
+            let fields = s.fields.iter().enumerate().map(|(i, f)| {
+                let field = match &f.ident {
+                    Some(ident) => quote::quote!(#ident),
+                    None => {
+                        let index = syn::Index::from(i);
+                        quote::quote!(#index)
+                    },
+                };
 
-                let calls = s.fields.iter().zip(names).map(|(f, i)| {
-                    let span = f.ty.span();
-                    quote::quote_spanned! {span=>
-                        QuickSer::ser(&self.#i, buffer)
-                    }
-                });
-                
-                quote::quote! {
-                    fn ser(&self, buffer: &mut Vec<u8>) {
-                        #(
-                            #calls;
-                        )*
-                    }
+                (f, quote::quote!(&self.#field))
+            });
+            let calls = fields.clone().map(|(f, value)| field_ser(f, value));
+            let lens = fields.map(|(f, value)| field_len(f, value));
+            
+            quote::quote! {
+                fn ser<__S: ::quick_proc::__private::QuickSink>(&self, buffer: &mut __S) {
+                    #version_ser
+                    #( #calls )*
                 }
-            } else {
-                let calls = s.fields.iter().map(|f| {
-                    let span = f.ty.span();
-                    let ident = f.ident.as_ref().unwrap();
-                    quote::quote_spanned! {span=>
-                        QuickSer::ser(&self.#ident, buffer)
-                    }
-                });
-                
-                quote::quote! {
-                    fn ser(&self, buffer: &mut Vec<u8>) {
-                        #(
-                            #calls;
-                        )*
-                    }
+
+                fn ser_len(&self) -> usize {
+                    0 #version_len #( + #lens )*
                 }
             }
         },
         syn::Data::Enum(e) => {
-            let variants = e.variants.iter().enumerate().map(|(i, v)| {
-                let ident = &v.ident;
-                let is_tuple = v.fields.iter().next().map(|f| f.ident.is_none()).unwrap_or(false);
-                let index = i as u8;
+            let tags = variant_tags(e);
+            let tag_type = enum_tag_type(&input.attrs, &tags);
+            let (variants, lens): (Vec<_>, Vec<_>) = e.variants.iter().zip(&tags).map(|(v, &tag)| {
+                let index = tag_literal(tag, &tag_type);
+                let (pattern, fields) = variant_pattern(name, v);
+                let calls = fields.iter().map(|(f, value)| field_ser(f, value.clone()));
+                let lens = fields.iter().map(|(f, value)| field_len(f, value.clone()));
 
-                if is_tuple {
-                    let fields = (0..v.fields.len()).map(|i| format_ident!("field{}", i));
-                    let fields2 = fields.clone();
-                    
+                (
                     quote::quote!(
-                        #name::#ident( #( #fields ),* ) => {
-                            QuickSer::ser(&#index, buffer);
-                            #(
-                                QuickSer::ser(#fields2, buffer);
-                            )*
+                        #pattern => {
+                            ::quick_proc::__private::QuickSer::ser(&#index, buffer);
+                            #( #calls )*
                         }
-                    )
-                } else {
-                    let fields = v.fields.iter().map(|f| f.ident.as_ref().unwrap());
-                    let fields2 = fields.clone();
-                    
+                    ),
                     quote::quote!(
-                        #name::#ident { #( #fields ),* } => {
-                            #index.ser(buffer);
-                            #(
-                                QuickSer::ser(#fields2, buffer);
-                            )*
-                        }
-                    )
-                }
-            });
+                        #pattern => ::quick_proc::__private::QuickSer::ser_len(&#index) #( + #lens )*,
+                    ),
+                )
+            }).unzip();
 
             quote::quote! {
-                fn ser(&self, buffer: &mut Vec<u8>) {
+                fn ser<__S: ::quick_proc::__private::QuickSink>(&self, buffer: &mut __S) {
                     match self {
                         #( #variants )*
                     }
                 }
+
+                fn ser_len(&self) -> usize {
+                    match self {
+                        #( #lens )*
+                    }
+                }
             }
         },
         syn::Data::Union(_) => panic!("union is not supported"),
     };
 
-    let de_ser = match &input.data {
+    let schema = quick_schema(&input);
+    let de_ser = de_ser_body(&input, &quote::quote!(::quick_proc::__private::QuickSer::try_de_ser_from));
+
+    quote::quote! {
+        impl #generics ::quick_proc::__private::QuickSer for #name #type_params {
+            const SCHEMA_HASH: u64 = #schema;
+
+            #ser
+
+            fn try_de_ser_from<__S: ::quick_proc::__private::QuickSource>(source: &mut __S) -> Result<Self, ::quick_proc::__private::DeError> {
+                #de_ser
+            }
+        }
+    }.into()
+}
+
+/// Borrowed decoding of structs and enums with `&str` and `&[T]` fields. Slices of 
+/// elements aligned to more than a byte are borrowed only if their data is aligned 
+/// in the input, which depends on the fields encoded before them, see `QuickDe`.
+#[proc_macro_derive(QuickDe, attributes(quick_ser, default))]
+pub fn derive_de(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    let name = &input.ident;
+
+    // the borrowed lifetime is the lifetime of the type if it has exactly one, 
+    // otherwise a fresh one outliving all of them
+    let lifetimes = input.generics.lifetimes().map(|l| l.lifetime.clone()).collect::<Vec<_>>();
+    let mut generics = input.generics.clone();
+    let lifetime = match lifetimes.as_slice() {
+        [lifetime] => lifetime.clone(),
+        _ => {
+            let lifetime = syn::Lifetime::new("'__de", proc_macro2::Span::call_site());
+            let mut param = syn::LifetimeDef::new(lifetime.clone());
+            param.bounds.extend(lifetimes.iter().cloned());
+            generics.params.insert(0, syn::GenericParam::Lifetime(param));
+            lifetime
+        },
+    };
+
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::quick_proc::__private::QuickDe<#lifetime>));
+    }
+
+    let mut type_params = input.generics.clone();
+    for param in type_params.type_params_mut() {
+        param.bounds.clear();
+    }
+
+    let de_ser = de_ser_body(&input, &quote::quote!(::quick_proc::__private::QuickDe::try_de_ser_borrowed_from));
+
+    quote::quote! {
+        impl #generics ::quick_proc::__private::QuickDe<#lifetime> for #name #type_params {
+            fn try_de_ser_borrowed_from<__S: ::quick_proc::__private::BorrowSource<#lifetime>>(
+                source: &mut __S,
+            ) -> Result<Self, ::quick_proc::__private::DeError> {
+                #de_ser
+            }
+        }
+    }.into()
+}
+
+#[proc_macro_derive(QuickArchive)]
+pub fn derive_archive(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let archived = format_ident!("Archived{}", name);
+
+    if !input.generics.params.is_empty() {
+        panic!("QuickArchive does not support generic types");
+    }
+
+    let data = match &input.data {
+        syn::Data::Struct(data) => data,
+        _ => panic!("QuickArchive only supports structs"),
+    };
+
+    let is_tuple = data.fields.iter().next().map(|f| f.ident.is_none()).unwrap_or(false);
+    let fields = data.fields.iter().enumerate().map(|(i, f)| match &f.ident {
+        Some(ident) => quote::quote!(#ident),
+        None => {
+            let index = syn::Index::from(i);
+            quote::quote!(#index)
+        },
+    }).collect::<Vec<_>>();
+    let types = data.fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+    let indices = (0..fields.len()).map(syn::Index::from);
+    let archived_fields = data.fields.iter().map(|f| {
+        let vis = &f.vis;
+        let ty = &f.ty;
+        match &f.ident {
+            Some(ident) => quote::quote!(#vis #ident: <#ty as ::quick_proc::__private::archive::Archive>::Archived),
+            None => quote::quote!(#vis <#ty as ::quick_proc::__private::archive::Archive>::Archived),
+        }
+    });
+
+    let definition = if is_tuple {
+        quote::quote!(#vis struct #archived( #( #archived_fields ),* );)
+    } else {
+        quote::quote!(#vis struct #archived { #( #archived_fields ),* })
+    };
+
+    let resolved = quote::quote! {
+        #( 
+            #fields: ::quick_proc::__private::archive::Archive::resolve(
+                &self.#fields, 
+                pos + std::mem::offset_of!(#archived, #fields), 
+                resolver.#indices,
+            ), 
+        )*
+    };
+
+    let message = format!(
+        "`{}` contains padding bytes, reorder the fields of `{}`", 
+        archived, name,
+    );
+
+    quote::quote! {
+        #[repr(C)]
+        #definition
+
+        const _: () = assert!(
+            std::mem::size_of::<#archived>() == 0 
+                #( + std::mem::size_of::<<#types as ::quick_proc::__private::archive::Archive>::Archived>() )*,
+            #message
+        );
+
+        unsafe impl ::quick_proc::__private::archive::Archive for #name {
+            type Archived = #archived;
+            type Resolver = ( #( <#types as ::quick_proc::__private::archive::Archive>::Resolver, )* );
+
+            fn archive_deps(&self, writer: &mut ::quick_proc::__private::archive::ArchiveWriter) -> Self::Resolver {
+                ( #( ::quick_proc::__private::archive::Archive::archive_deps(&self.#fields, writer), )* )
+            }
+
+            unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver) -> Self::Archived {
+                #archived { #resolved }
+            }
+
+            fn verify(buffer: &[u8], pos: usize) -> Result<(), ::quick_proc::__private::DeError> {
+                #( 
+                    <#types as ::quick_proc::__private::archive::Archive>::verify(
+                        buffer, 
+                        pos + std::mem::offset_of!(#archived, #fields),
+                    )?; 
+                )*
+                Ok(())
+            }
+        }
+    }.into()
+}
+
+fn de_ser_body(input: &syn::DeriveInput, de_call: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let name = &input.ident;
+    match &input.data {
         syn::Data::Struct(s) => {
             let is_tuple = s.fields.iter().next().map(|f| f.ident.is_none()).unwrap_or(false);
-            if is_tuple {
-                let names = std::iter::repeat(format_ident!("QuickSer")).take(s.fields.len());
+            let calls = s.fields.iter().map(|f| field_de(f, de_call));
 
-                quote::quote! {
-                    fn de_ser(progress: &mut usize, buffer: &[u8]) -> Self {
-                        Self(#(
-                            #names::de_ser(progress, buffer),
-                        )*)
+            let version = struct_version(&input.attrs);
+            for field in &s.fields {
+                if let Some(since) = field_attrs(field).since {
+                    if version.map_or(true, |v| since > v) {
+                        panic!("quick_ser since = {} exceeds the version of `{}`", since, name);
                     }
                 }
+            }
+            let version_check = version.map(|version| quote::quote! {
+                let offset = ::quick_proc::__private::QuickSource::offset(source);
+                let version = <u32 as ::quick_proc::__private::QuickVarint>::try_de_ser_varint(source)?;
+                if version > #version {
+                    return Err(::quick_proc::__private::DeError::UnsupportedVersion { offset });
+                }
+            });
+
+            if is_tuple {
+                quote::quote! {
+                    #version_check
+                    Ok(Self(#(
+                        #calls,
+                    )*))
+                }
             } else {
                 let names = s.fields.iter().map(|f| f.ident.as_ref().unwrap());
                 
                 quote::quote! {
-                    fn de_ser(progress: &mut usize, buffer: &[u8]) -> Self {
-                        Self {#(
-                            #names: QuickSer::de_ser(progress, buffer),
-                        )*}
-                    }
+                    #version_check
+                    Ok(Self {#(
+                        #names: #calls,
+                    )*})
                 }
             }
         },
         syn::Data::Enum(e) => {
-            let variants = e.variants.iter().enumerate().map(|(i, v)| {
+            let tags = variant_tags(e);
+            let tag_type = enum_tag_type(&input.attrs, &tags);
+            let variants = e.variants.iter().zip(&tags).map(|(v, &tag)| {
                 let ident = &v.ident;
                 let is_tuple = v.fields.iter().next().map(|f| f.ident.is_none()).unwrap_or(false);
-                let index = i as u8;
+                let index = tag_literal(tag, &tag_type);
+                if v.fields.iter().any(|f| field_attrs(f).since.is_some()) {
+                    panic!("quick_ser since is only supported on struct fields");
+                }
+                let calls = v.fields.iter().map(|f| field_de(f, de_call));
 
                 if is_tuple {
-                    let fields = v.fields.iter().map(|f| {
-                        let span = f.ty.span();
-                        quote::quote_spanned!(span =>
-                            QuickSer::de_ser(progress, buffer)
-                        )
-                    });
-                    
                     quote::quote!(
                         #index => {
-                            #name::#ident(#(
-                                #fields,
-                            )*)
+                            Ok(#name::#ident(#(
+                                #calls,
+                            )*))
                         }
                     )
                 } else {
-                    let fields = v.fields.iter().map(|f| { 
-                        let span = f.ty.span();
-                        let ident = f.ident.as_ref().unwrap();
-                        let call = quote::quote_spanned!(span =>
-                            QuickSer::de_ser(progress, buffer)
-                        );
-
-                        quote::quote_spanned!(span =>
-                            #ident: #call
-                        )
-                    });
+                    let names = v.fields.iter().map(|f| f.ident.as_ref().unwrap());
                     
                     quote::quote!(
                         #index => {
-                            #name::#ident {#(
-                                #fields,
-                            )*}
+                            Ok(#name::#ident {#(
+                                #names: #calls,
+                            )*})
                         }
                     )
                 }
             });
 
             quote::quote! {
-                fn de_ser(progress: &mut usize, buffer: &[u8]) -> Self {
-                    match QuickSer::de_ser(progress, buffer) {
-                        #( #variants )*
-                        v => panic!("invalid variant {:?}", v),
-                    }
+                let offset = ::quick_proc::__private::QuickSource::offset(source);
+                match <#tag_type as ::quick_proc::__private::QuickSer>::try_de_ser_from(source)? {
+                    #( #variants )*
+                    _ => Err(::quick_proc::__private::DeError::InvalidTag { offset }),
                 }
             }
         },
         syn::Data::Union(_) => panic!("union is not supported"),
+    }
+}
+
+fn quick_schema(input: &syn::DeriveInput) -> proc_macro2::TokenStream {
+    let name = &input.ident;
+    let fields_schema = |fields: &syn::Fields| {
+        // fields added with `since` are left out so that evolving a versioned struct
+        // keeps accepting framed payloads of older versions, the version header 
+        // decides which fields are present
+        let fields = fields.iter().enumerate().filter(|(_, f)| {
+            let attrs = field_attrs(f);
+            !attrs.skip && attrs.since.is_none()
+        });
+        let description = fields.clone().map(|(i, f)| {
+            let attrs = field_attrs(f);
+            let mut field = format!("{}:{}", field_name(f, i), type_name(&f.ty));
+            if attrs.varint {
+                field.push_str(" varint");
+            }
+            if let Some(with) = attrs.with {
+                field.push_str(&format!(" with={}", quote::quote!(#with)));
+            }
+            field
+        }).collect::<Vec<_>>().join(",");
+        let types = fields
+            .filter(|(_, f)| field_attrs(f).with.is_none() && !mentions(&f.ty, name))
+            .map(|(_, f)| &f.ty);
+
+        (description, quote::quote! {
+            #( let hash = ::quick_proc::__private::schema_combine(hash, <#types as ::quick_proc::__private::QuickSer>::SCHEMA_HASH); )*
+        })
     };
 
-    quote::quote! {
-        impl #generics QuickSer for #name #type_params {
-            #ser
-            #de_ser
+    match &input.data {
+        syn::Data::Struct(s) => {
+            let (fields, nested) = fields_schema(&s.fields);
+            schema_hash(format!("{}{{{}}}", name, fields), nested)
+        },
+        syn::Data::Enum(e) => {
+            let tags = variant_tags(e);
+            let tag_type = enum_tag_type(&input.attrs, &tags);
+            let (variants, nested): (Vec<_>, Vec<_>) = e.variants.iter().zip(&tags).map(|(v, tag)| {
+                let (fields, nested) = fields_schema(&v.fields);
+                (format!("{}={}({})", v.ident, tag, fields), nested)
+            }).unzip();
+
+            schema_hash(
+                format!("{}:{}{{{}}}", name, tag_type, variants.join(",")), 
+                quote::quote!(#( #nested )*),
+            )
+        },
+        syn::Data::Union(_) => panic!("union is not supported"),
+    }
+}
+
+fn schema_hash(description: String, nested: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    quote::quote! {{
+        let hash = ::quick_proc::__private::schema_hash(#description);
+        #nested
+        hash
+    }}
+}
+
+fn field_name(field: &syn::Field, index: usize) -> String {
+    match &field.ident {
+        Some(ident) => ident.to_string(),
+        None => index.to_string(),
+    }
+}
+
+fn type_name(ty: &syn::Type) -> String {
+    quote::quote!(#ty).to_string().replace(' ', "")
+}
+
+/// Whether `ty` refers to the type being derived, whose hash can not be composed 
+/// into its own.
+fn mentions(ty: &syn::Type, name: &syn::Ident) -> bool {
+    fn walk(tokens: proc_macro2::TokenStream, name: &syn::Ident) -> bool {
+        tokens.into_iter().any(|token| match token {
+            proc_macro2::TokenTree::Ident(ident) => ident == *name || ident == "Self",
+            proc_macro2::TokenTree::Group(group) => walk(group.stream(), name),
+            _ => false,
+        })
+    }
+
+    walk(quote::quote!(#ty), name)
+}
+
+struct FieldAttrs {
+    skip: bool,
+    varint: bool,
+    default: Option<proc_macro2::TokenStream>,
+    with: Option<syn::Path>,
+    since: Option<u32>,
+    sorted: bool,
+}
+
+fn field_attrs(field: &syn::Field) -> FieldAttrs {
+    let args = quick_ser_args(&field.attrs, &["varint", "skip", "default", "with", "since", "sorted"]);
+
+    let default = arg_value(&args, "default")
+        .map(|expr| quote::quote!(#expr))
+        .or_else(|| field.attrs.iter()
+            .find(|a| a.path.is_ident("default"))
+            .map(|a| a.tokens.clone()));
+
+    let with = arg_value(&args, "with").map(|expr| match expr {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit), .. }) => lit.parse::<syn::Path>()
+            .unwrap_or_else(|err| panic!("invalid quick_ser with path: {}", err)),
+        _ => panic!("quick_ser with expects a string literal"),
+    });
+
+    let attrs = FieldAttrs {
+        skip: has_arg(&args, "skip"),
+        varint: has_arg(&args, "varint"),
+        default,
+        with,
+        since: int_arg(&args, "since"),
+        sorted: has_arg(&args, "sorted"),
+    };
+
+    if [attrs.skip, attrs.varint, attrs.with.is_some(), attrs.sorted].iter().filter(|&&a| a).count() > 1 {
+        panic!("quick_ser attributes skip, varint, with and sorted are mutually exclusive");
+    }
+
+    if attrs.skip && attrs.since.is_some() {
+        panic!("quick_ser attributes skip and since are mutually exclusive");
+    }
+
+    attrs
+}
+
+fn field_ser(field: &syn::Field, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let attrs = field_attrs(field);
+    let span = field.ty.span();
+
+    if attrs.skip {
+        quote::quote!()
+    } else if let Some(with) = attrs.with {
+        quote::quote_spanned!(span => #with::ser(#value, buffer);)
+    } else if attrs.varint {
+        quote::quote_spanned!(span => ::quick_proc::__private::QuickVarint::ser_varint(#value, buffer);)
+    } else if attrs.sorted {
+        quote::quote_spanned!(span => ::quick_proc::__private::sorted::ser(#value, buffer);)
+    } else {
+        quote::quote_spanned!(span => ::quick_proc::__private::QuickSer::ser(#value, buffer);)
+    }
+}
+
+/// Pattern binding the fields of `variant` that are serialized.
+fn variant_pattern<'a>(
+    name: &syn::Ident, 
+    variant: &'a syn::Variant,
+) -> (proc_macro2::TokenStream, Vec<(&'a syn::Field, proc_macro2::TokenStream)>) {
+    let ident = &variant.ident;
+    let is_tuple = variant.fields.iter().next().map(|f| f.ident.is_none()).unwrap_or(false);
+    let mut bound = vec![];
+
+    let fields = variant.fields.iter().enumerate().map(|(i, f)| {
+        let skip = field_attrs(f).skip;
+        let binding = match &f.ident {
+            Some(ident) => ident.clone(),
+            None => format_ident!("field{}", i),
+        };
+        if !skip {
+            bound.push((f, quote::quote!(#binding)));
         }
-    }.into()
+        match (is_tuple, skip) {
+            (true, true) => quote::quote!(_),
+            (true, false) => quote::quote!(#binding),
+            (false, true) => quote::quote!(#binding: _),
+            (false, false) => quote::quote!(#binding),
+        }
+    }).collect::<Vec<_>>();
+
+    let pattern = if is_tuple {
+        quote::quote!(#name::#ident( #( #fields ),* ))
+    } else {
+        quote::quote!(#name::#ident { #( #fields ),* })
+    };
+
+    (pattern, bound)
+}
+
+fn field_len(field: &syn::Field, value: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let attrs = field_attrs(field);
+    let span = field.ty.span();
+
+    if attrs.skip {
+        quote::quote!(0)
+    } else if let Some(with) = attrs.with {
+        quote::quote_spanned!(span => {
+            let mut counter = ::quick_proc::__private::sink::CountingSink::new();
+            #with::ser(#value, &mut counter);
+            counter.count()
+        })
+    } else if attrs.varint {
+        quote::quote_spanned!(span => ::quick_proc::__private::QuickVarint::varint_len(#value))
+    } else {
+        quote::quote_spanned!(span => ::quick_proc::__private::QuickSer::ser_len(#value))
+    }
+}
+
+fn field_de(field: &syn::Field, de_call: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let attrs = field_attrs(field);
+    let span = field.ty.span();
+    let default = attrs.default.unwrap_or_else(|| quote::quote_spanned!(span => Default::default()));
+
+    let call = if attrs.skip {
+        return default;
+    } else if let Some(with) = attrs.with {
+        quote::quote_spanned!(span => #with::try_de_ser(source)?)
+    } else if attrs.varint {
+        quote::quote_spanned!(span => ::quick_proc::__private::QuickVarint::try_de_ser_varint(source)?)
+    } else {
+        quote::quote_spanned!(span => #de_call(source)?)
+    };
+
+    match attrs.since {
+        Some(since) => quote::quote! {
+            if version >= #since { #call } else { #default }
+        },
+        None => call,
+    }
 }
 
 #[proc_macro_derive(QuickEnumGets)]
@@ -370,6 +955,163 @@ pub fn derive_custom_default(input: TokenStream) -> TokenStream {
     ).into()
 }
 
+fn has_stable_repr(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter()
+        .filter(|a| a.path.is_ident("repr"))
+        .filter_map(|a| a.parse_args_with(Punctuated::<syn::Ident, Token![,]>::parse_terminated).ok())
+        .flatten()
+        .any(|repr| repr == "C" || repr == "transparent")
+}
+
+/// Bytes taken by a portable enum discriminant, as many as it does in memory, which
+/// is either the `#[repr]` width or the smallest one fitting all discriminants.
+fn enum_width(attrs: &[syn::Attribute]) -> proc_macro2::TokenStream {
+    match enum_repr(attrs) {
+        Some(repr) if repr == "usize" || repr == "isize" => quote::quote!(8),
+        _ => quote::quote!(std::mem::size_of::<Self>()),
+    }
+}
+
+/// Primitive integer named in the `#[repr]` of an enum.
+fn enum_repr(attrs: &[syn::Attribute]) -> Option<syn::Ident> {
+    const INTEGERS: &[&str] = &[
+        "u8", "u16", "u32", "u64", "u128", "usize", 
+        "i8", "i16", "i32", "i64", "i128", "isize",
+    ];
+    attrs.iter()
+        .filter(|a| a.path.is_ident("repr"))
+        .filter_map(|a| a.parse_args_with(Punctuated::<syn::Ident, Token![,]>::parse_terminated).ok())
+        .flatten()
+        .find(|repr| INTEGERS.iter().any(|i| repr == i))
+}
+
+struct QuickSerArg {
+    name: syn::Ident,
+    value: Option<syn::Expr>,
+}
+
+impl Parse for QuickSerArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self { name, value })
+    }
+}
+
+fn quick_ser_args(attrs: &[syn::Attribute], allowed: &[&str]) -> Vec<QuickSerArg> {
+    let args = attrs.iter()
+        .filter(|a| a.path.is_ident("quick_ser"))
+        .flat_map(|a| a.parse_args_with(Punctuated::<QuickSerArg, Token![,]>::parse_terminated)
+            .unwrap_or_else(|err| panic!("invalid quick_ser attribute: {}", err)))
+        .collect::<Vec<_>>();
+
+    for arg in &args {
+        if !allowed.iter().any(|&a| arg.name == a) {
+            panic!("unknown quick_ser attribute `{}`", arg.name);
+        }
+    }
+
+    args
+}
+
+fn has_arg(args: &[QuickSerArg], name: &str) -> bool {
+    args.iter().any(|a| a.name == name)
+}
+
+fn arg_value<'a>(args: &'a [QuickSerArg], name: &str) -> Option<&'a syn::Expr> {
+    args.iter()
+        .find(|a| a.name == name)
+        .map(|a| a.value.as_ref().unwrap_or_else(|| panic!("quick_ser attribute `{}` expects a value", name)))
+}
+
+fn int_arg(args: &[QuickSerArg], name: &str) -> Option<u32> {
+    arg_value(args, name).map(|expr| match expr {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit), .. }) => lit.base10_parse()
+            .unwrap_or_else(|err| panic!("invalid quick_ser {}: {}", name, err)),
+        _ => panic!("quick_ser {} expects an integer literal", name),
+    })
+}
+
+fn struct_version(attrs: &[syn::Attribute]) -> Option<u32> {
+    int_arg(&quick_ser_args(attrs, &["version"]), "version")
+}
+
+fn variant_tags(e: &syn::DataEnum) -> Vec<u64> {
+    // discriminants double as tags only when all of them are non-negative literals,
+    // negative or computed ones leave the tags in declaration order
+    let literal_discriminants = e.variants.iter().all(|v| matches!(
+        &v.discriminant, 
+        None | Some((_, syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(_), .. }))),
+    ));
+    let mut next = 0;
+    let tags = e.variants.iter().map(|v| {
+        let args = quick_ser_args(&v.attrs, &["tag"]);
+        let discriminant = v.discriminant.as_ref()
+            .filter(|_| literal_discriminants)
+            .map(|(_, d)| d);
+        let explicit = arg_value(&args, "tag").or(discriminant);
+        let tag = match explicit {
+            Some(syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(lit), .. })) => lit.base10_parse()
+                .unwrap_or_else(|err| panic!("invalid tag of variant `{}`: {}", v.ident, err)),
+            Some(_) => panic!("quick_ser tag of variant `{}` must be an integer literal", v.ident),
+            None => next,
+        };
+        next = tag + 1;
+        tag
+    }).collect::<Vec<u64>>();
+
+    for (i, tag) in tags.iter().enumerate() {
+        if let Some(j) = tags[..i].iter().position(|t| t == tag) {
+            panic!(
+                "variants `{}` and `{}` share the tag {}", 
+                e.variants[j].ident, e.variants[i].ident, tag,
+            );
+        }
+    }
+
+    tags
+}
+
+fn enum_tag_type(attrs: &[syn::Attribute], tags: &[u64]) -> syn::Ident {
+    let args = quick_ser_args(attrs, &["tag"]);
+    let max_tag = tags.iter().copied().max().unwrap_or(0);
+    let fitting = match max_tag {
+        0..=0xFF => "u8",
+        0x100..=0xFFFF => "u16",
+        0x10000..=0xFFFF_FFFF => "u32",
+        _ => panic!("tag {} does not fit into u32", max_tag),
+    };
+
+    match arg_value(&args, "tag") {
+        Some(syn::Expr::Path(path)) => {
+            let ident = path.path.get_ident()
+                .unwrap_or_else(|| panic!("quick_ser tag type must be one of u8, u16 or u32"));
+            let width = match ident.to_string().as_str() {
+                "u8" => 0,
+                "u16" => 1,
+                "u32" => 2,
+                _ => panic!("quick_ser tag type must be one of u8, u16 or u32"),
+            };
+            if width < ["u8", "u16", "u32"].iter().position(|&t| t == fitting).unwrap() {
+                panic!("tag {} does not fit into tag type `{}`", max_tag, ident);
+            }
+            ident.clone()
+        },
+        Some(_) => panic!("quick_ser tag type must be one of u8, u16 or u32"),
+        None => format_ident!("{}", fitting),
+    }
+}
+
+fn tag_literal(tag: u64, tag_type: &syn::Ident) -> syn::LitInt {
+    syn::LitInt::new(&format!("{}{}", tag, tag_type), proc_macro2::Span::call_site())
+}
+
 fn pascal_to_snake(s: &str) -> String {
     let mut result = String::with_capacity(s.len() + s.chars().filter(|c| c.is_uppercase()).count());
     let mut prev_is_upper = true;
@@ -1,17 +1,144 @@
+//! Deriving [`RealQuickSer`] rejects types whose layout contains padding, since
+//! the padding bytes would be copied into the output uninitialized:
+//!
+//! ```compile_fail
+//! use quick_proc::*;
+//!
+//! #[derive(RealQuickSer, Clone, Copy)]
+//! struct P { a: u8, b: u32 }
+//! ```
+//!
+//! Such types can still opt into the bulk copy, accepting that the padding is
+//! serialized as is:
+//!
+//! ```
+//! use quick_proc::*;
+//!
+//! #[derive(RealQuickSer, Clone, Copy, PartialEq, Debug)]
+//! #[quick_ser(allow_padding)]
+//! struct P { a: u8, b: u32 }
+//!
+//! let bytes = P { a: 1, b: 2 }.to_bytes();
+//! assert_eq!(P::de_ser(&mut 0, &bytes), P { a: 1, b: 2 });
+//! ```
+
 pub use traits::*;
 pub use derive::*;
 
+// lets the derives refer to `::quick_proc` from within this crate's own tests
+extern crate self as quick_proc;
+
+/// Paths the derived code is expanded against, so that it does not depend on 
+/// what the deriving module imports.
+#[doc(hidden)]
+pub mod __private {
+    pub use traits::*;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[derive(Debug, QuickSer)]
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
     pub enum TestEnum {
         A(u8),
         B(u16),
         C(u32, u64),
     }
 
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
+    pub struct VarintCase {
+        #[quick_ser(varint)]
+        len: u64,
+        #[quick_ser(varint)]
+        delta: i32,
+        fixed: u32,
+    }
+
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
+    pub enum VarintEnum {
+        A(#[quick_ser(varint)] u128),
+        B { #[quick_ser(varint)] value: isize },
+    }
+
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
+    #[quick_ser(tag = u16)]
+    pub enum WideTagEnum {
+        A(u8),
+        B,
+    }
+
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
+    #[repr(u16)]
+    pub enum StableTagEnum {
+        #[quick_ser(tag = 7)]
+        Inserted(u8),
+        A = 2,
+        B,
+        #[quick_ser(tag = 300)]
+        C { value: u32 },
+    }
+
+    const SHIFTED: i8 = 1 << 4;
+
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
+    #[repr(i8)]
+    pub enum SignedDiscriminants {
+        A = -1,
+        B = 1,
+        C = SHIFTED,
+    }
+
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
+    pub struct FieldAttrCase {
+        kept: u8,
+        #[quick_ser(skip)]
+        cache: Vec<u8>,
+        #[quick_ser(skip)]
+        #[default(NonDefault(1))]
+        non_default: NonDefault,
+        #[quick_ser(skip, default = String::from("fallback"))]
+        label: String,
+        #[quick_ser(with = "ipv4")]
+        address: std::net::Ipv4Addr,
+    }
+
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
+    pub enum FieldAttrEnum {
+        A(#[quick_ser(skip)] u8, u16),
+        B { #[quick_ser(skip)] skipped: u8, kept: u16 },
+    }
+
+    mod ipv4 {
+        use super::*;
+
+        pub fn ser<S: QuickSink>(address: &std::net::Ipv4Addr, buffer: &mut S) {
+            buffer.write(&address.octets());
+        }
+
+        pub fn try_de_ser<S: QuickSource>(source: &mut S) -> Result<std::net::Ipv4Addr, DeError> {
+            let mut octets = [0; 4];
+            octets.copy_from_slice(source.take(4)?);
+            Ok(octets.into())
+        }
+    }
+
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
+    #[quick_ser(version = 1)]
+    pub struct ConfigV1 {
+        name: String,
+    }
+
+    #[derive(Debug, QuickSer, PartialEq, Eq)]
+    #[quick_ser(version = 2)]
+    pub struct ConfigV2 {
+        name: String,
+        #[quick_ser(since = 2, default = 8)]
+        threads: u32,
+        #[quick_ser(since = 2)]
+        tags: Vec<String>,
+    }
+
     #[derive(PartialEq, Eq, Debug)]
     pub struct NonDefault(u8);
 
@@ -45,16 +172,26 @@ mod tests {
         tuple_opt: Option<(u8, u8, u8)>,
         tuple_vec: Vec<(u8, u8, u8)>,
         tuple_vec_opt: Vec<Option<(u8, u8, u8)>>,
+        uint_opt: Option<u8>,
     }
 
     #[derive(Clone, Copy, RealQuickSer, PartialEq, Eq, Debug)]
     pub struct RealQuickSerBaseCase {
         tuple: (u8, u8, u8),
         uint: u8,
-        uint_opt: Option<u8>,
+        ch: char,
         usize: usize,
     }
 
+    #[derive(Clone, Copy, RealQuickSer, PartialEq, Eq, Debug)]
+    pub enum RealQuickSerEnum {
+        A,
+        B = 3,
+    }
+
+    #[derive(Clone, Copy, RealQuickSer, PartialEq, Eq, Debug)]
+    pub struct RealQuickSerTuple(bool, RealQuickSerEnum);
+
     #[test]
     fn ser_base_case() {
         let a = QuickSerBaseCase {
@@ -64,6 +201,7 @@ mod tests {
             tuple_opt: Some((10, 11, 12)),
             tuple_vec: vec![(13, 14, 15), (16, 17, 18)],
             tuple_vec_opt: vec![Some((19, 20, 21)), None],
+            uint_opt: Some(26),
         };
 
         test_ser_de(&a);
@@ -71,16 +209,1131 @@ mod tests {
         let b = RealQuickSerBaseCase {
             tuple: (22, 23, 24),
             uint: 25,
-            uint_opt: Some(26),
+            ch: 'x',
             usize: 27,
         };
 
         test_ser_de(&b);
     }
 
+    #[test]
+    fn de_ser_errors() {
+        test_ser_de(&TestEnum::C(1, 2));
+        test_ser_de(&vec![TestEnum::A(3), TestEnum::B(4)]);
+
+        let mut buffer = Vec::new();
+        String::from("hello").ser(&mut buffer);
+        buffer.pop();
+        assert!(matches!(
+            String::try_de_ser(&mut 0, &buffer), 
+            Err(DeError::UnexpectedEnd { .. })
+        ));
+
+        assert_eq!(
+            Option::<u8>::try_de_ser(&mut 0, &[2]), 
+            Err(DeError::InvalidTag { offset: 0 })
+        );
+        assert_eq!(
+            TestEnum::try_de_ser(&mut 0, &[3]), 
+            Err(DeError::InvalidTag { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn string_validation() {
+        let mut buffer = Vec::new();
+        String::from("hello").ser(&mut buffer);
+        let last = buffer.len() - 1;
+        buffer[last] = 0xFF;
+        assert_eq!(
+            String::try_de_ser(&mut 0, &buffer), 
+            Err(DeError::InvalidUtf8 { offset: last })
+        );
+
+        let value = Some(vec![String::from("trusted")]);
+        let mut buffer = Vec::new();
+        value.ser(&mut buffer);
+        let mut progress = 0;
+        let result = unsafe { Option::<Vec<String>>::try_de_ser_trusted(&mut progress, &buffer) };
+        assert_eq!(result, Ok(value));
+        assert_eq!(progress, buffer.len());
+    }
+
+    #[test]
+    fn quick_bounds() {
+        assert_eq!(
+            u32::try_de_ser(&mut 1, &[0; 4]), 
+            Err(DeError::UnexpectedEnd { offset: 1 })
+        );
+
+        let mut buffer = vec![0];
+        0x0102_0304u32.ser(&mut buffer);
+        assert_eq!(u32::try_de_ser(&mut 1, &buffer), Ok(0x0102_0304));
+
+        let mut buffer = Vec::new();
+        vec![1u64, 2, 3].ser(&mut buffer);
+        buffer.pop();
+        assert!(matches!(
+            Vec::<u64>::try_de_ser(&mut 0, &buffer), 
+            Err(DeError::UnexpectedEnd { .. })
+        ));
+
+        let mut buffer = Vec::new();
+        usize::MAX.ser_varint(&mut buffer);
+        assert!(matches!(
+            Vec::<u64>::try_de_ser(&mut 0, &buffer), 
+            Err(DeError::LengthOverflow { .. })
+        ));
+    }
+
+    #[test]
+    fn quick_validation() {
+        test_ser_de(&vec![RealQuickSerTuple(true, RealQuickSerEnum::B)]);
+
+        assert_eq!(bool::try_de_ser(&mut 0, &[2]), Err(DeError::InvalidValue { offset: 0 }));
+        assert_eq!(
+            char::try_de_ser(&mut 0, &0xD800u32.to_ne_bytes()), 
+            Err(DeError::InvalidValue { offset: 0 })
+        );
+        #[cfg(not(feature = "portable"))]
+        assert_eq!(
+            RealQuickSerTuple::try_de_ser(&mut 0, &[1, 1]), 
+            Err(DeError::InvalidValue { offset: 0 })
+        );
+        #[cfg(feature = "portable")]
+        assert_eq!(
+            RealQuickSerTuple::try_de_ser(&mut 0, &[1, 1]), 
+            Err(DeError::InvalidTag { offset: 1 })
+        );
+        assert_eq!(
+            RealQuickSerTuple::try_de_ser(&mut 0, &[2, 3]), 
+            Err(DeError::InvalidValue { offset: 0 })
+        );
+
+        let mut buffer = Vec::new();
+        vec![true, false, true].ser(&mut buffer);
+        let last = buffer.len() - 1;
+        buffer[last] = 7;
+        assert_eq!(
+            Vec::<bool>::try_de_ser(&mut 0, &buffer), 
+            Err(DeError::InvalidValue { offset: last })
+        );
+    }
+
+    #[test]
+    fn portable_encoding() {
+        let mut buffer = Vec::new();
+        0x0102_0304u32.ser_portable(&mut buffer);
+        7usize.ser_portable(&mut buffer);
+        RealQuickSerEnum::B.ser_portable(&mut buffer);
+        assert_eq!(buffer, [4, 3, 2, 1, 7, 0, 0, 0, 0, 0, 0, 0, 3]);
+
+        let value = RealQuickSerBaseCase {
+            tuple: (1, 2, 3),
+            uint: 4,
+            ch: 'y',
+            usize: 5,
+        };
+        let mut buffer = Vec::new();
+        value.ser_portable(&mut buffer);
+        assert_eq!(buffer.len(), 3 + 1 + 4 + 8);
+        let mut source = SliceSource::new(&buffer, 0);
+        assert_eq!(RealQuickSerBaseCase::try_de_ser_portable(&mut source), Ok(value));
+        assert_eq!(source.offset(), buffer.len());
+    }
+
+    #[derive(Clone, Copy, RealQuickSer, PartialEq, Eq, Debug)]
+    #[repr(u8)]
+    pub enum ByteEnum {
+        A = 1,
+        B = 200,
+    }
+
+    #[derive(Clone, Copy, RealQuickSer, PartialEq, Eq, Debug)]
+    #[repr(i16)]
+    pub enum WideEnum {
+        A = -300,
+        B = 5,
+    }
+
+    #[test]
+    fn portable_enum_width() {
+        let mut buffer = Vec::new();
+        ByteEnum::B.ser_portable(&mut buffer);
+        WideEnum::A.ser_portable(&mut buffer);
+        RealQuickSerEnum::B.ser_portable(&mut buffer);
+        assert_eq!(buffer, [200, 0xD4, 0xFE, 3]);
+
+        let mut source = SliceSource::new(&buffer, 0);
+        assert_eq!(ByteEnum::try_de_ser_portable(&mut source), Ok(ByteEnum::B));
+        assert_eq!(WideEnum::try_de_ser_portable(&mut source), Ok(WideEnum::A));
+        assert_eq!(RealQuickSerEnum::try_de_ser_portable(&mut source), Ok(RealQuickSerEnum::B));
+        assert_eq!(ByteEnum::try_de_ser_portable(&mut SliceSource::new(&[2], 0)), Err(DeError::InvalidTag { offset: 0 }));
+
+        // a single byte discriminant is laid out the same on every host
+        const { assert!(ByteEnum::PORTABLE_LAYOUT && ByteEnum::QUICK) };
+        test_ser_de(&vec![ByteEnum::A, ByteEnum::B]);
+        test_ser_de(&vec![WideEnum::A, WideEnum::B]);
+    }
+
+    #[derive(Clone, Copy, RealQuickSer, PartialEq, Eq, Debug)]
+    #[quick_ser(allow_padding)]
+    pub struct PaddedCase {
+        flag: u8,
+        value: u32,
+    }
+
+    #[test]
+    fn allow_padding() {
+        let value = PaddedCase { flag: 1, value: 0x0102_0304 };
+        test_ser_de(&value);
+        test_ser_de(&vec![value; 3]);
+        assert_eq!(value.ser_len(), if PORTABLE { 1 + 4 } else { 8 });
+    }
+
+    #[test]
+    fn varint_encoding() {
+        let mut buffer = Vec::new();
+        vec![String::from("a"); 200].ser(&mut buffer);
+        assert_eq!(&buffer[..3], &[0xC8, 0x01, 0x01]);
+        test_ser_de(&vec![String::from("a"); 200]);
+
+        let mut buffer = Vec::new();
+        VarintCase { len: 300, delta: -2, fixed: 1 }.ser(&mut buffer);
+        assert_eq!(buffer.len(), 2 + 1 + 4);
+        test_ser_de(&VarintCase { len: u64::MAX, delta: i32::MIN, fixed: 1 });
+        test_ser_de(&VarintEnum::A(u128::MAX));
+        test_ser_de(&VarintEnum::B { value: isize::MIN });
+
+        let mut buffer = Vec::new();
+        u64::MAX.ser_varint(&mut buffer);
+        assert_eq!(
+            u32::try_de_ser_varint(&mut SliceSource::new(&buffer, 0)), 
+            Err(DeError::InvalidValue { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn enum_tag_width() {
+        let mut buffer = Vec::new();
+        WideTagEnum::A(1).ser(&mut buffer);
+        WideTagEnum::B.ser(&mut buffer);
+        assert_eq!(buffer.len(), 2 + 1 + 2);
+        test_ser_de(&vec![WideTagEnum::A(1), WideTagEnum::B]);
+
+        assert_eq!(
+            WideTagEnum::try_de_ser(&mut 0, &2u16.to_ne_bytes()), 
+            Err(DeError::InvalidTag { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn explicit_enum_tags() {
+        let mut buffer = Vec::new();
+        StableTagEnum::Inserted(1).ser(&mut buffer);
+        StableTagEnum::A.ser(&mut buffer);
+        StableTagEnum::B.ser(&mut buffer);
+        assert_eq!(
+            buffer, 
+            [&7u16.to_ne_bytes()[..], &[1], &2u16.to_ne_bytes(), &3u16.to_ne_bytes()].concat()
+        );
+        test_ser_de(&vec![
+            StableTagEnum::Inserted(1), 
+            StableTagEnum::A, 
+            StableTagEnum::B, 
+            StableTagEnum::C { value: 4 },
+        ]);
+
+        // negative and computed discriminants are not tags, the declaration order is
+        assert_eq!(SignedDiscriminants::B.to_bytes(), [1]);
+        assert_eq!(SignedDiscriminants::C.to_bytes(), [2]);
+        test_ser_de(&vec![SignedDiscriminants::A, SignedDiscriminants::B, SignedDiscriminants::C]);
+    }
+
+    #[test]
+    fn field_attributes() {
+        let value = FieldAttrCase {
+            kept: 1,
+            cache: vec![2, 3],
+            non_default: NonDefault(4),
+            label: String::from("label"),
+            address: std::net::Ipv4Addr::new(127, 0, 0, 1),
+        };
+        let mut buffer = Vec::new();
+        value.ser(&mut buffer);
+        assert_eq!(buffer, [1, 127, 0, 0, 1]);
+        assert_eq!(FieldAttrCase::de_ser(&mut 0, &buffer), FieldAttrCase {
+            cache: vec![],
+            non_default: NonDefault(1),
+            label: String::from("fallback"),
+            ..value
+        });
+
+        let mut buffer = Vec::new();
+        FieldAttrEnum::B { skipped: 1, kept: 2 }.ser(&mut buffer);
+        assert_eq!(FieldAttrEnum::de_ser(&mut 0, &buffer), FieldAttrEnum::B { skipped: 0, kept: 2 });
+        test_ser_de(&FieldAttrEnum::A(0, 3));
+    }
+
+    #[test]
+    fn versioned_structs() {
+        let mut buffer = Vec::new();
+        ConfigV1 { name: String::from("old") }.ser(&mut buffer);
+        assert_eq!(ConfigV2::de_ser(&mut 0, &buffer), ConfigV2 {
+            name: String::from("old"),
+            threads: 8,
+            tags: vec![],
+        });
+
+        let new = ConfigV2 {
+            name: String::from("new"),
+            threads: 2,
+            tags: vec![String::from("fast")],
+        };
+        test_ser_de(&new);
+
+        let mut buffer = Vec::new();
+        new.ser(&mut buffer);
+        assert_eq!(
+            ConfigV1::try_de_ser(&mut 0, &buffer), 
+            Err(DeError::UnsupportedVersion { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn framing() {
+        let value = ConfigV2 {
+            name: String::from("framed"),
+            threads: 4,
+            tags: vec![String::from("a")],
+        };
+
+        let mut buffer = vec![0xAA];
+        frame::write(&value, &mut buffer);
+        let mut progress = 1;
+        assert_eq!(frame::read::<ConfigV2>(&mut progress, &buffer), Ok(value));
+        assert_eq!(progress, buffer.len());
+
+        assert!(matches!(
+            frame::read::<ConfigV1>(&mut 1, &buffer),
+            Err(frame::FrameError::TypeMismatch { .. })
+        ));
+
+        let mut corrupted = buffer.clone();
+        corrupted[30] ^= 1;
+        assert!(matches!(
+            frame::read::<ConfigV2>(&mut 1, &corrupted),
+            Err(frame::FrameError::ChecksumMismatch { .. })
+        ));
+
+        assert_eq!(frame::read::<ConfigV2>(&mut 0, &buffer), Err(frame::FrameError::BadMagic));
+        assert_eq!(frame::crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn streaming() {
+        let value = QuickSerBaseCase {
+            indices: (0..100_000).collect(),
+            data: vec![7; 20_000],
+            tuple: (1, 2, 3),
+            tuple_opt: Some((4, 5, 6)),
+            tuple_vec: vec![(7, 8, 9); 3],
+            tuple_vec_opt: vec![None, Some((10, 11, 12))],
+            uint_opt: None,
+        };
+
+        let mut file = Vec::new();
+        stream::write(&value, &mut file).unwrap();
+        let mut buffer = Vec::new();
+        value.ser(&mut buffer);
+        assert_eq!(file, buffer);
+
+        // a reader that hands out a few bytes at a time
+        struct Trickle<'a>(&'a [u8]);
+
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let len = buf.len().min(self.0.len()).min(7);
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+
+        assert_eq!(stream::read::<QuickSerBaseCase>(Trickle(&file)).unwrap(), value);
+
+        let mut source = stream::ReadSource::new(Trickle(&file[..file.len() - 1]));
+        let err = source.decode::<QuickSerBaseCase>().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        let mut pair = Vec::new();
+        let mut sink = stream::WriteSink::new(&mut pair);
+        String::from("first").ser(&mut sink);
+        ConfigV1 { name: String::from("second") }.ser(&mut sink);
+        sink.finish().unwrap();
+        let mut source = stream::ReadSource::new(&pair[..]);
+        assert_eq!(source.decode::<String>().unwrap(), "first");
+        assert_eq!(source.decode::<ConfigV1>().unwrap(), ConfigV1 { name: String::from("second") });
+        assert_eq!(
+            source.decode::<bool>().unwrap_err().kind(), 
+            std::io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn sinks() {
+        let value = TestEnum::C(1, 2);
+        let mut expected = Vec::new();
+        value.ser(&mut expected);
+
+        let mut storage = [0; 32];
+        let mut sink = sink::SliceSink::new(&mut storage);
+        value.ser(&mut sink);
+        assert_eq!(sink.finish().unwrap(), &expected[..]);
+
+        let mut storage = [0; 8];
+        let mut sink = sink::SliceSink::new(&mut storage);
+        value.ser(&mut sink);
+        assert_eq!(
+            sink.finish(), 
+            Err(sink::SinkOverflow { needed: expected.len(), capacity: 8 })
+        );
+
+        let mut sink = sink::ArraySink::<16>::new();
+        value.ser(&mut sink);
+        assert_eq!(sink.finish().unwrap(), &expected[..]);
+        String::from("overflow").ser(&mut sink);
+        assert!(sink.finish().is_err());
+
+        let mut sink = sink::CountingSink::new();
+        QuickSerBaseCase {
+            indices: vec![1, 2],
+            data: vec![3; 300],
+            tuple: (1, 2, 3),
+            tuple_opt: None,
+            tuple_vec: vec![],
+            tuple_vec_opt: vec![Some((4, 5, 6))],
+            uint_opt: Some(7),
+        }.ser(&mut sink);
+        assert_eq!(sink.count(), 1 + 16 + 2 + 300 + 3 + 1 + 1 + 1 + 4 + 2);
+    }
+
+    #[test]
+    fn serialized_length() {
+        fn check<T: QuickSer>(value: T) {
+            let mut buffer = Vec::new();
+            value.ser(&mut buffer);
+            assert_eq!(value.ser_len(), buffer.len());
+        }
+
+        check(String::from("length"));
+        check(vec![String::new(); 130]);
+        check(vec![(1u8, 2u16); 10]);
+        check(Some(std::collections::HashMap::from([(1u32, vec![true]), (2, vec![])])));
+        check(std::time::SystemTime::now());
+        check(RealQuickSerTuple(true, RealQuickSerEnum::B));
+        check(VarintCase { len: 1 << 40, delta: -70, fixed: 3 });
+        check(StableTagEnum::C { value: 1 });
+        check(FieldAttrCase {
+            kept: 1,
+            cache: vec![1, 2],
+            non_default: NonDefault(3),
+            label: String::from("skipped"),
+            address: std::net::Ipv4Addr::LOCALHOST,
+        });
+        check(FieldAttrEnum::B { skipped: 1, kept: 2 });
+        check(ConfigV2 { name: String::from("len"), threads: 1, tags: vec![] });
+
+        assert_eq!(0u64.ser_len(), 8);
+        assert_eq!(u64::MAX.varint_len(), 10);
+        assert_eq!((-1i32).varint_len(), 1);
+    }
+
+    #[test]
+    fn single_reservation() {
+        struct Reallocs(Vec<u8>, usize);
+
+        impl QuickSink for Reallocs {
+            fn write(&mut self, bytes: &[u8]) {
+                let capacity = self.0.capacity();
+                self.0.extend_from_slice(bytes);
+                self.1 += (capacity != self.0.capacity()) as usize;
+            }
+
+            fn reserve(&mut self, additional: usize) {
+                self.0.reserve(additional);
+            }
+        }
+
+        let value = QuickSerBaseCase {
+            indices: (0..1000).collect(),
+            data: vec![1; 5000],
+            tuple: (1, 2, 3),
+            tuple_opt: Some((4, 5, 6)),
+            tuple_vec: vec![(7, 8, 9); 100],
+            tuple_vec_opt: vec![Some((1, 2, 3)); 100],
+            uint_opt: Some(4),
+        };
+
+        let mut sink = Reallocs(Vec::new(), 0);
+        value.ser(&mut sink);
+        assert!(sink.1 > 1);
+
+        let mut sink = Reallocs(Vec::new(), 0);
+        value.ser_reserved(&mut sink);
+        assert_eq!(sink.1, 0);
+        assert_eq!(sink.0, value.to_bytes());
+
+        let mut buffer = vec![0; 3];
+        let capacity = buffer.capacity();
+        value.ser_reserved(&mut buffer);
+        assert!(buffer.capacity() < (capacity + value.ser_len()) * 2);
+    }
+
+    #[derive(QuickSer)]
+    pub struct OwnedRecord {
+        name: String,
+        words: Vec<u32>,
+        #[quick_ser(varint)]
+        id: u64,
+        tags: Vec<String>,
+    }
+
+    #[derive(QuickDe, Debug, PartialEq)]
+    pub struct BorrowedRecord<'a> {
+        name: &'a str,
+        words: &'a [u32],
+        #[quick_ser(varint)]
+        id: u64,
+        tags: Vec<String>,
+    }
+
+    #[derive(QuickSer)]
+    pub enum OwnedMessage {
+        Text(String),
+        Raw { data: Vec<u8> },
+    }
+
+    #[derive(QuickDe, Debug, PartialEq)]
+    pub enum BorrowedMessage<'a, 'b> {
+        Text(&'a str),
+        Raw { data: &'b [u8] },
+    }
+
+    #[test]
+    fn borrowed_decoding() {
+        #[repr(align(8))]
+        struct Aligned([u8; 64]);
+
+        let owned = OwnedRecord {
+            name: String::from("abc"),
+            words: vec![1, 2, 3],
+            id: 300,
+            tags: vec![String::from("t")],
+        };
+        let bytes = owned.to_bytes();
+        // `words` data starts at byte 5, right after the name and its own length,
+        // shifting by 3 puts it on a 4 byte boundary
+        let pad = 3;
+        let mut storage = Aligned([0; 64]);
+        storage.0[pad..pad + bytes.len()].copy_from_slice(&bytes);
+        let buffer = &storage.0[..pad + bytes.len()];
+
+        let mut progress = pad;
+        let record = BorrowedRecord::try_de_ser_borrowed(&mut progress, buffer).unwrap();
+        assert_eq!(progress, buffer.len());
+        assert_eq!(record, BorrowedRecord { name: "abc", words: &[1, 2, 3], id: 300, tags: vec![String::from("t")] });
+        assert_eq!(record.name.as_ptr(), buffer[pad + 1..].as_ptr());
+
+        let mut progress = pad + 1;
+        let mut shifted = Aligned([0; 64]);
+        shifted.0[pad + 1..pad + 1 + bytes.len()].copy_from_slice(&bytes);
+        assert_eq!(
+            BorrowedRecord::try_de_ser_borrowed(&mut progress, &shifted.0),
+            Err(DeError::Misaligned { offset: pad + 1 + 5 })
+        );
+
+        let bytes = OwnedMessage::Raw { data: vec![9, 8] }.to_bytes();
+        assert_eq!(
+            BorrowedMessage::try_de_ser_borrowed(&mut 0, &bytes), 
+            Ok(BorrowedMessage::Raw { data: &[9, 8] })
+        );
+        let bytes = OwnedMessage::Text(String::from("hi")).to_bytes();
+        assert_eq!(BorrowedMessage::try_de_ser_borrowed(&mut 0, &bytes), Ok(BorrowedMessage::Text("hi")));
+        assert_eq!(<&str>::try_de_ser_borrowed(&mut 0, &[1, 0xFF]), Err(DeError::InvalidUtf8 { offset: 1 }));
+        assert_eq!(<&[bool]>::try_de_ser_borrowed(&mut 0, &[2, 1, 2]), Err(DeError::InvalidValue { offset: 2 }));
+    }
+
+    #[derive(QuickArchive)]
+    pub struct LookupTable {
+        id: u64,
+        keys: Vec<u64>,
+        name: String,
+        index: LookupIndex,
+    }
+
+    #[derive(QuickArchive)]
+    pub struct LookupIndex(Vec<(u32, u32)>, u64);
+
+    #[test]
+    fn archived_access() {
+        let table = LookupTable {
+            id: 7,
+            keys: vec![1, 3, 5],
+            name: String::from("primes"),
+            index: LookupIndex(vec![(1, 2), (3, 4)], 9),
+        };
+        let bytes = archive::to_bytes(&table);
+
+        let mut storage = vec![0u64; bytes.len().div_ceil(8) + 1];
+        let aligned = unsafe { 
+            std::slice::from_raw_parts_mut(storage.as_mut_ptr() as *mut u8, storage.len() * 8) 
+        };
+        aligned[..bytes.len()].copy_from_slice(&bytes);
+
+        let archived = archive::access::<LookupTable>(&aligned[..bytes.len()]).unwrap();
+        assert_eq!(archived.id, 7);
+        assert_eq!(archived.keys.as_slice(), &[1, 3, 5]);
+        assert_eq!(&*archived.name, "primes");
+        assert_eq!(archived.index.0.as_slice(), &[(1, 2), (3, 4)]);
+        assert_eq!(archived.index.1, 9);
+
+        // the root sits at the end, break the offset of `keys` which follows `id`
+        let root = bytes.len() - std::mem::size_of::<ArchivedLookupTable>();
+        aligned[root + 8..root + 16].copy_from_slice(&i64::MAX.to_ne_bytes());
+        assert_eq!(
+            archive::access::<LookupTable>(&aligned[..bytes.len()]).err(),
+            Some(DeError::UnexpectedEnd { offset: root + 8 })
+        );
+
+        aligned[1..bytes.len() + 1].copy_from_slice(&bytes);
+        assert!(matches!(
+            archive::access::<LookupTable>(&aligned[1..bytes.len() + 1]),
+            Err(DeError::Misaligned { .. })
+        ));
+    }
+
+    #[test]
+    fn file_helpers() {
+        let dir = std::env::temp_dir().join(format!("quick-proc-fs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.bin");
+
+        let value = ConfigV2 { name: String::from("saved"), threads: 3, tags: vec![] };
+        fs::save(&path, &value).unwrap();
+        fs::save(&path, &value).unwrap();
+        assert_eq!(fs::load::<ConfigV2>(&path).unwrap(), value);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        let limited = limits::Limits { max_len: 5, ..limits::Limits::default() };
+        assert_eq!(fs::load_limited::<ConfigV2>(&path, limited).unwrap(), value);
+        let limited = limits::Limits { max_len: 4, ..limits::Limits::default() };
+        assert_eq!(
+            fs::load_limited::<ConfigV2>(&path, limited).unwrap_err().kind(), 
+            std::io::ErrorKind::InvalidData
+        );
+
+        let err = fs::load::<ConfigV1>(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.push(0);
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(fs::load::<ConfigV2>(&path).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+        assert_eq!(
+            fs::load::<ConfigV2>(dir.join("missing")).unwrap_err().kind(), 
+            std::io::ErrorKind::NotFound
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    mod schema_old {
+        use super::*;
+
+        #[derive(RealQuickSer, Clone, Copy)]
+        #[repr(u8)]
+        pub enum Kind {
+            A,
+            B,
+        }
+
+        #[derive(RealQuickSer, Clone, Copy)]
+        #[quick_ser(allow_padding)]
+        #[repr(C)]
+        pub struct Padded {
+            pub value: u32,
+        }
+
+        #[derive(QuickSer)]
+        pub struct Entry {
+            pub id: u32,
+            pub data: Vec<u8>,
+        }
+    }
+
+    mod schema_new {
+        use super::*;
+
+        #[derive(RealQuickSer, Clone, Copy)]
+        #[repr(u32)]
+        pub enum Kind {
+            A,
+            B,
+        }
+
+        #[derive(RealQuickSer, Clone, Copy)]
+        #[quick_ser(allow_padding)]
+        #[repr(C, align(8))]
+        pub struct Padded {
+            pub value: u32,
+        }
+
+        #[derive(QuickSer)]
+        pub struct Entry {
+            pub id: u64,
+            pub data: Vec<u8>,
+        }
+    }
+
+    #[derive(QuickSer)]
+    pub struct Tree {
+        value: Option<schema_old::Entry>,
+        children: Vec<Tree>,
+    }
+
+    #[test]
+    fn schema_fingerprint() {
+        assert_ne!(schema_old::Entry::SCHEMA_HASH, schema_new::Entry::SCHEMA_HASH);
+        // layout changes that leave the fields as they are
+        assert_ne!(schema_old::Kind::SCHEMA_HASH, schema_new::Kind::SCHEMA_HASH);
+        assert_eq!(schema_old::Padded::SCHEMA_HASH != schema_new::Padded::SCHEMA_HASH, !PORTABLE);
+        assert_ne!(<Vec<u8>>::SCHEMA_HASH, <Vec<u16>>::SCHEMA_HASH);
+        assert_ne!(<(u8, u16)>::SCHEMA_HASH, <(u16, u8)>::SCHEMA_HASH);
+        assert_ne!(Tree::SCHEMA_HASH, 0);
+        assert_ne!(VarintCase::SCHEMA_HASH, FieldAttrCase::SCHEMA_HASH);
+        assert_eq!(
+            <Vec<RealQuickSerBaseCase>>::SCHEMA_HASH, 
+            schema_combine(schema_hash("Vec"), RealQuickSerBaseCase::SCHEMA_HASH)
+        );
+
+        // fields added through versioning keep the fingerprint stable,
+        // so framed payloads of older versions still load
+        #[derive(QuickSer)]
+        #[quick_ser(version = 1)]
+        pub struct Config {
+            name: String,
+        }
+
+        let mut framed = Vec::new();
+        frame::write(&Config { name: String::from("old") }, &mut framed);
+        let old = Config::SCHEMA_HASH;
+        {
+            #[derive(QuickSer, Debug, PartialEq, Eq)]
+            #[quick_ser(version = 2)]
+            pub struct Config {
+                name: String,
+                #[quick_ser(since = 2)]
+                threads: u32,
+            }
+
+            assert_eq!(old, Config::SCHEMA_HASH);
+            assert_eq!(
+                frame::read::<Config>(&mut 0, &framed), 
+                Ok(Config { name: String::from("old"), threads: 0 })
+            );
+        }
+    }
+
+    #[test]
+    fn std_types() {
+        use std::{
+            borrow::Cow,
+            collections::{BTreeMap, BTreeSet, BinaryHeap, HashSet, LinkedList, VecDeque},
+            ffi::OsString,
+            num::{NonZeroU32, NonZeroI64, Wrapping},
+            path::PathBuf,
+            rc::Rc,
+            sync::Arc,
+            time::Duration,
+        };
+
+        test_ser_de(&BTreeMap::from([(1u32, "a".to_string()), (2, "b".to_string())]));
+        test_ser_de(&BTreeSet::from(["x".to_string(), "y".to_string()]));
+        test_ser_de(&HashSet::from([1u64, 2, 3]));
+        test_ser_de(&LinkedList::from([Some(1u8), None]));
+        test_ser_de(&Box::new(10u16));
+        test_ser_de(&Box::<str>::from("boxed"));
+        test_ser_de(&Rc::new(vec!["rc".to_string()]));
+        test_ser_de(&Arc::<[u32]>::from(vec![1, 2, 3]));
+        test_ser_de(&Cow::<str>::Borrowed("cow"));
+        test_ser_de(&Cow::<[u8]>::Borrowed(&[1, 2]));
+        test_ser_de(&Ok::<u32, String>(1));
+        test_ser_de(&Err::<u32, String>("failed".to_string()));
+        test_ser_de(&(3u64..7));
+        test_ser_de(&(3u64..=7));
+        test_ser_de(&Duration::new(5, 999));
+        test_ser_de(&PathBuf::from("/tmp/cache.bin"));
+        test_ser_de(&OsString::from("name"));
+        test_ser_de(&vec![NonZeroU32::new(4).unwrap(), NonZeroU32::new(9).unwrap()]);
+        test_ser_de(&NonZeroI64::new(-1).unwrap());
+        test_ser_de(&Wrapping(250u8));
+
+        // a deque that wraps around its buffer is written as two slices
+        let mut deque = VecDeque::with_capacity(4);
+        deque.extend([1u32, 2, 3]);
+        deque.pop_front();
+        deque.extend([4, 5]);
+        assert_eq!(deque.as_slices().1.len(), 1);
+        test_ser_de(&deque);
+        assert_eq!(deque.ser_len(), 1 + 4 * 4);
+
+        let heap = BinaryHeap::from(vec![3u8, 9, 1]);
+        let mut progress = 0;
+        let result = BinaryHeap::<u8>::de_ser(&mut progress, &heap.to_bytes());
+        assert_eq!(result.into_sorted_vec(), vec![1, 3, 9]);
+
+        // same encoding as the owned equivalents
+        assert_eq!(Box::<str>::from("same").to_bytes(), "same".to_string().to_bytes());
+        assert_eq!(Box::<[u16]>::SCHEMA_HASH, Vec::<u16>::SCHEMA_HASH);
+        assert_eq!(Rc::<u8>::SCHEMA_HASH, u8::SCHEMA_HASH);
+
+        let mut progress = 0;
+        let zero = 0u32.to_bytes();
+        assert!(matches!(
+            NonZeroU32::try_de_ser(&mut progress, &zero),
+            Err(DeError::InvalidValue { offset: 0 })
+        ));
+        let mut progress = 0;
+        let nanos = (1u64, 1_000_000_000u32).to_bytes();
+        assert!(matches!(
+            Duration::try_de_ser(&mut progress, &nanos),
+            Err(DeError::InvalidValue { .. })
+        ));
+    }
+
+    #[derive(RealQuickSer, Clone, Copy, Debug, PartialEq, Eq)]
+    #[repr(C)]
+    pub struct Block {
+        hash: [u8; 32],
+        matrix: [[u32; 2]; 2],
+    }
+
+    #[test]
+    fn arrays() {
+        let block = Block { hash: [7; 32], matrix: [[1, 2], [3, 4]] };
+        test_ser_de(&block);
+        assert_eq!(block.ser_len(), 32 + 16);
+        test_ser_de(&["a".to_string(), "b".to_string()]);
+        test_ser_de(&[Some(1u8), None, Some(3)]);
+        test_ser_de(&[0u64; 0]);
+
+        // arrays of quick elements are copied in bulk, without lengths
+        let hashes = vec![[1u8; 32], [2; 32], [3; 32]];
+        test_ser_de(&hashes);
+        assert_eq!(hashes.ser_len(), 1 + 3 * 32);
+        const { assert!(<[u8; 32]>::QUICK && !<[String; 2]>::QUICK) };
+        assert_ne!(<[u8; 32]>::SCHEMA_HASH, <[u8; 16]>::SCHEMA_HASH);
+
+        // element validation still applies to the bulk path
+        let mut progress = 0;
+        assert!(matches!(
+            <Vec<[bool; 2]>>::try_de_ser(&mut progress, &[2, 0, 0, 1, 2]),
+            Err(DeError::InvalidValue { offset: 3 })
+        ));
+    }
+
+    #[test]
+    fn tuples() {
+        test_ser_de(&());
+        test_ser_de(&(1u8,));
+        test_ser_de(&("name".to_string(), 3u32));
+        test_ser_de(&vec![(Some(1u16), vec![true]), (None, vec![])]);
+
+        // std does not implement `Eq` and `Debug` past 12 elements
+        let wide = (
+            1u8, 2u16, 3u32, 4u64, 5u128, 6i8, 7i16, 8i32,
+            9i64, 10i128, 'k', true, "m".to_string(), Some(14u8), vec![15u8], (16u8, 17u8),
+        );
+        let bytes = wide.to_bytes();
+        assert_eq!(wide.ser_len(), bytes.len());
+        let mut progress = 0;
+        let result = <(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, char, bool, String, Option<u8>, Vec<u8>, (u8, u8))>
+            ::de_ser(&mut progress, &bytes);
+        assert_eq!(result.to_bytes(), bytes);
+        assert_eq!(result.12, "m");
+
+        // padding makes the tuple encode field by field, without the padding bytes
+        assert_eq!((1u8, 2u32).ser_len(), 5);
+        assert_eq!(((), 1u8).ser_len(), 1);
+        const { 
+            assert!(<(u32, u32)>::QUICK != PORTABLE);
+            assert!(!<(u8, u32)>::QUICK && !<(String, u32)>::QUICK);
+        };
+        assert_ne!(<(String, u32)>::SCHEMA_HASH, <(u32, String)>::SCHEMA_HASH);
+    }
+
+    #[derive(QuickSer, Debug, PartialEq, Eq)]
+    pub struct Manifest {
+        #[quick_ser(sorted)]
+        files: std::collections::HashMap<String, u64>,
+        #[quick_ser(sorted)]
+        tags: std::collections::HashSet<u32>,
+    }
+
+    #[test]
+    fn deterministic_maps() {
+        use std::{collections::{HashMap, HashSet}, hash::{BuildHasherDefault, DefaultHasher}};
+        use sorted::Sorted;
+
+        // every map gets a differently seeded `RandomState`, so iteration orders differ
+        let entries = (0..64u32).map(|i| (i.to_string(), i as u64)).collect::<Vec<_>>();
+        let maps = (0..8)
+            .map(|i| {
+                let mut entries = entries.clone();
+                entries.rotate_left(i);
+                entries.into_iter().collect::<HashMap<_, _>>()
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = Sorted(maps[0].clone()).to_bytes();
+        for map in &maps {
+            assert_eq!(Sorted(map.clone()).to_bytes(), bytes);
+        }
+        assert_eq!(bytes.len(), maps[0].ser_len());
+        assert_eq!(Sorted::<HashMap<String, u64>>::SCHEMA_HASH, HashMap::<String, u64>::SCHEMA_HASH);
+        let mut progress = 0;
+        assert_eq!(HashMap::<String, u64>::de_ser(&mut progress, &bytes), maps[0]);
+
+        let manifest = |i: usize| Manifest { 
+            files: maps[i].clone(), 
+            tags: (0..32).map(|t| t * 7).collect(),
+        };
+        test_ser_de(&manifest(0));
+        assert_eq!(manifest(1).to_bytes(), manifest(2).to_bytes());
+
+        // only the outer map is ordered, nested sets need their own wrapper
+        let nested = || (0..4u8)
+            .map(|k| (k, Sorted((0..32u32).collect::<HashSet<_>>())))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(Sorted(nested()).to_bytes(), Sorted(nested()).to_bytes());
+
+        // custom hashers
+        type Fixed = BuildHasherDefault<DefaultHasher>;
+        let map = entries.iter().cloned().collect::<HashMap<String, u64, Fixed>>();
+        test_ser_de(&map);
+        let set = (0..16u8).collect::<HashSet<u8, Fixed>>();
+        test_ser_de(&set);
+        test_ser_de(&Sorted(set));
+    }
+
+    #[test]
+    fn decode_limits() {
+        use limits::{Limits, LimitedSource};
+
+        // a corrupted length is rejected before allocating and otherwise 
+        // fails on missing input instead of reserving the declared capacity
+        let mut huge = Vec::new();
+        (u32::MAX as usize).ser_varint(&mut huge);
+        let limited = Limits { max_len: 1000, ..Limits::default() };
+        assert_eq!(
+            limits::try_de_ser::<Vec<String>>(&mut 0, &huge, limited),
+            Err(DeError::LimitExceeded { offset: 5 })
+        );
+        assert_eq!(
+            Vec::<String>::try_de_ser(&mut 0, &huge), 
+            Err(DeError::UnexpectedEnd { offset: 5 })
+        );
+        assert_eq!(
+            String::try_de_ser(&mut 0, &huge), 
+            Err(DeError::UnexpectedEnd { offset: 5 })
+        );
+
+        let strings = vec!["0123456789".to_string(); 3];
+        let bytes = strings.to_bytes();
+        let needed = 3 * std::mem::size_of::<String>() + 30;
+        let mut source = LimitedSource::new(SliceSource::new(&bytes, 0), Limits { 
+            max_alloc: needed, 
+            ..Limits::default() 
+        });
+        assert_eq!(Vec::<String>::try_de_ser_from(&mut source), Ok(strings.clone()));
+        assert_eq!(source.allocated(), needed);
+        let limited = Limits { max_alloc: needed - 1, ..Limits::default() };
+        assert!(matches!(
+            limits::try_de_ser::<Vec<String>>(&mut 0, &bytes, limited),
+            Err(DeError::LimitExceeded { .. })
+        ));
+
+        // nesting
+        let mut tree = Tree { value: None, children: vec![] };
+        for _ in 0..100 {
+            tree = Tree { value: None, children: vec![tree] };
+        }
+        let bytes = tree.to_bytes();
+        let shallow = Limits { max_depth: 50, ..Limits::default() };
+        assert!(matches!(
+            limits::try_de_ser::<Tree>(&mut 0, &bytes, shallow),
+            Err(DeError::LimitExceeded { .. })
+        ));
+        let deep = Limits { max_depth: 101, ..Limits::default() };
+        assert!(limits::try_de_ser::<Tree>(&mut 0, &bytes, deep).is_ok());
+        let boxed = Some(Box::new(Some(Box::new(1u8))));
+        let limited = Limits { max_depth: 1, ..Limits::default() };
+        assert!(limits::try_de_ser::<Option<Box<Option<Box<u8>>>>>(&mut 0, &boxed.to_bytes(), limited).is_err());
+
+        // duplicate keys
+        let duplicated = [2, 1, 10, 1, 20];
+        let strict = Limits { strict: true, ..Limits::default() };
+        let mut progress = 0;
+        let map = std::collections::HashMap::<u8, u8>::de_ser(&mut progress, &duplicated);
+        assert_eq!(map, std::collections::HashMap::from([(1, 20)]));
+        assert_eq!(
+            limits::try_de_ser::<std::collections::HashMap<u8, u8>>(&mut 0, &duplicated, strict),
+            Err(DeError::DuplicateKey { offset: 3 })
+        );
+        assert_eq!(
+            limits::try_de_ser::<std::collections::BTreeSet<u8>>(&mut 0, &[2, 7, 7], strict),
+            Err(DeError::DuplicateKey { offset: 2 })
+        );
+
+        // the other entry points for untrusted input
+        let limited = Limits { max_len: 2, ..Limits::default() };
+        let bytes = strings.to_bytes();
+        let mut framed = Vec::new();
+        frame::write(&strings, &mut framed);
+        assert!(matches!(
+            frame::read_limited::<Vec<String>>(&mut 0, &framed, limited),
+            Err(frame::FrameError::Decode(DeError::LimitExceeded { .. }))
+        ));
+        assert_eq!(
+            stream::read_limited::<Vec<String>>(&bytes[..], limited).unwrap_err().kind(), 
+            std::io::ErrorKind::InvalidData
+        );
+        assert_eq!(stream::read_limited::<Vec<String>>(&bytes[..], Limits::default()).unwrap(), strings);
+
+        #[derive(QuickDe, Debug)]
+        #[allow(dead_code)]
+        pub struct Named<'a> {
+            name: &'a str,
+            tags: Vec<String>,
+        }
+
+        let bytes = ("name".to_string(), strings.clone()).to_bytes();
+        assert_eq!(limits::try_de_ser_borrowed::<Named>(&mut 0, &bytes, Limits::default()).unwrap().tags, strings);
+        assert!(matches!(
+            limits::try_de_ser_borrowed::<Named>(&mut 0, &bytes, limited),
+            Err(DeError::LimitExceeded { .. })
+        ));
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    pub enum Never {}
+
+    impl RealQuickSer for Never {
+        const PORTABLE_LAYOUT: bool = true;
+        const QUICK_SCHEMA_HASH: u64 = schema_hash("Never");
+
+        fn ser_portable<S: QuickSink>(&self, _buffer: &mut S) {
+            match *self {}
+        }
+
+        fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+            Err(DeError::InvalidValue { offset: source.offset() })
+        }
+
+        fn is_valid(_bytes: &[u8]) -> bool {
+            false
+        }
+    }
+
+    impl QuickSer for Never {
+        gen_quick_copy!();
+    }
+
+    #[test]
+    fn zero_sized_validation() {
+        // no bytes are read for zero sized elements, yet an uninhabited type
+        // must never be produced from a non-empty length
+        assert_eq!(<Vec<Never>>::try_de_ser(&mut 0, &[2]).err(), Some(DeError::InvalidValue { offset: 1 }));
+        assert!(<Vec<Never>>::try_de_ser(&mut 0, &[0]).unwrap().is_empty());
+        assert!(<[Never; 1]>::try_de_ser(&mut 0, &[]).is_err());
+        assert!(<[Never; 0]>::try_de_ser(&mut 0, &[]).is_ok());
+        assert_eq!(<&[Never]>::try_de_ser_borrowed(&mut 0, &[1]).err(), Some(DeError::InvalidValue { offset: 1 }));
+        test_ser_de(&vec![(); 3]);
+    }
+
+    mod minimal_imports {
+        use crate::{QuickArchive, QuickDe, QuickSer, RealQuickSer};
+
+        #[derive(RealQuickSer, Clone, Copy, PartialEq, Eq, Debug)]
+        pub struct Point(u32, u32);
+
+        #[derive(RealQuickSer, Clone, Copy, PartialEq, Eq, Debug)]
+        #[repr(u8)]
+        pub enum Kind {
+            A,
+            B,
+        }
+
+        #[derive(QuickSer, PartialEq, Eq, Debug)]
+        #[quick_ser(version = 2)]
+        pub struct Record<T> {
+            #[quick_ser(varint)]
+            id: u64,
+            #[quick_ser(sorted)]
+            tags: std::collections::HashMap<u8, u8>,
+            #[quick_ser(with = "super::ipv4")]
+            address: std::net::Ipv4Addr,
+            #[quick_ser(since = 2)]
+            points: Vec<Point>,
+            kind: Kind,
+            value: T,
+        }
+
+        #[derive(QuickSer, PartialEq, Eq, Debug)]
+        #[quick_ser(tag = u16)]
+        pub enum Message {
+            #[quick_ser(tag = 300)]
+            Ping,
+            Data(Vec<u8>),
+        }
+
+        #[derive(QuickDe, PartialEq, Eq, Debug)]
+        pub struct Borrowed<'a> {
+            name: &'a str,
+        }
+
+        #[derive(QuickArchive)]
+        pub struct Table {
+            keys: Vec<u64>,
+        }
+
+        #[test]
+        fn derives_without_glob_imports() {
+            let record = Record {
+                id: 300,
+                tags: [(1, 2), (3, 4)].into_iter().collect(),
+                address: std::net::Ipv4Addr::LOCALHOST,
+                points: vec![Point(1, 2)],
+                kind: Kind::B,
+                value: String::from("v"),
+            };
+            let bytes = record.to_bytes();
+            assert_eq!(bytes.len(), record.ser_len());
+            assert_eq!(Record::<String>::try_de_ser(&mut 0, &bytes), Ok(record));
+            assert_eq!(Message::try_de_ser(&mut 0, &Message::Ping.to_bytes()), Ok(Message::Ping));
+            assert_eq!(Message::try_de_ser(&mut 0, &Message::Data(vec![1]).to_bytes()), Ok(Message::Data(vec![1])));
+
+            let bytes = String::from("abc").to_bytes();
+            assert_eq!(Borrowed::try_de_ser_borrowed(&mut 0, &bytes), Ok(Borrowed { name: "abc" }));
+            assert!(crate::archive::to_bytes(&Table { keys: vec![1] }).len() > 8);
+        }
+    }
+
     fn test_ser_de<T: QuickSer + PartialEq<T> + Eq + std::fmt::Debug>(t: &T) {
         let mut buffer = Vec::new();
         t.ser(&mut buffer);
+        assert_eq!(t.ser_len(), buffer.len());
         let mut progress = 0;
         let result = T::de_ser(&mut progress, &buffer);
         assert_eq!(progress, buffer.len());
@@ -0,0 +1,281 @@
+//! Archived format that is read in place, without decoding.
+//!
+//! [`RealQuickSer`] values are stored as they are in memory, `Vec<T>` and `String`
+//! are stored as an offset relative to their own position plus a length, so a
+//! (memory mapped) buffer produced by [`to_bytes`] can be viewed through [`access`]
+//! as `&T::Archived`. Archives use the host layout, regardless of [`crate::PORTABLE`].
+//!
+//! Archived values are only sound to use in place, so building one by hand is unsafe:
+//!
+//! ```compile_fail
+//! use traits::archive::Archive;
+//!
+//! let archived = vec![1u64, 2, 3].resolve(0, 1 << 40);
+//! ```
+
+use std::{marker::PhantomData, ops::Deref};
+
+use crate::{DeError, RealQuickSer};
+
+/// # Safety
+///
+/// `verify` must reject every buffer whose archived value, when viewed in place,
+/// would break the invariants of `Self::Archived`.
+pub unsafe trait Archive {
+    type Archived;
+    type Resolver;
+
+    /// Writes the data `Self::Archived` points to, archived values are written
+    /// after everything they refer to.
+    fn archive_deps(&self, writer: &mut ArchiveWriter) -> Self::Resolver;
+
+    /// Creates the archived value that is about to be written at `pos`.
+    ///
+    /// # Safety
+    ///
+    /// `resolver` must come from [`Archive::archive_deps`] of `self` on the writer 
+    /// the result is written into at `pos`. Archived values refer to their data 
+    /// relative to their own position, so the result may only be used through
+    /// the archived bytes, never on its own.
+    unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver) -> Self::Archived;
+
+    /// Validates the archived value at `pos`, `buffer` is known to contain
+    /// `size_of::<Self::Archived>()` bytes at `pos`.
+    fn verify(buffer: &[u8], pos: usize) -> Result<(), DeError>;
+}
+
+#[derive(Default)]
+pub struct ArchiveWriter {
+    buffer: Vec<u8>,
+}
+
+impl ArchiveWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pos(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Pads the buffer with zeros until its length is a multiple of `align`.
+    pub fn align(&mut self, align: usize) {
+        let padding = (align - self.buffer.len() % align) % align;
+        self.buffer.resize(self.buffer.len() + padding, 0);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> usize {
+        let pos = self.pos();
+        self.buffer.extend_from_slice(bytes);
+        pos
+    }
+
+    /// Writes `value` at the next position aligned for `T` and returns the position.
+    ///
+    /// # Safety
+    ///
+    /// `T` must not contain padding bytes.
+    pub unsafe fn write_value<T>(&mut self, value: &T) -> usize {
+        self.align(std::mem::align_of::<T>());
+        self.write_bytes(std::slice::from_raw_parts(
+            value as *const T as *const u8,
+            std::mem::size_of::<T>()
+        ))
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+pub fn to_bytes<T: Archive>(value: &T) -> Vec<u8> {
+    let mut writer = ArchiveWriter::new();
+    let resolver = value.archive_deps(&mut writer);
+    writer.align(std::mem::align_of::<T::Archived>());
+    unsafe {
+        let archived = value.resolve(writer.pos(), resolver);
+        writer.write_value(&archived);
+    }
+    writer.into_bytes()
+}
+
+/// Validates `bytes` produced by [`to_bytes`] and views the root value in place.
+/// `bytes` has to be aligned at least as the most aligned type in the archive.
+pub fn access<T: Archive>(bytes: &[u8]) -> Result<&T::Archived, DeError> {
+    let pos = bytes.len().checked_sub(std::mem::size_of::<T::Archived>())
+        .ok_or(DeError::UnexpectedEnd { offset: bytes.len() })?;
+    if bytes[pos..].as_ptr() as usize % std::mem::align_of::<T::Archived>() != 0 {
+        return Err(DeError::Misaligned { offset: pos });
+    }
+    T::verify(bytes, pos)?;
+    Ok(unsafe { access_unchecked::<T>(bytes) })
+}
+
+/// # Safety
+///
+/// `bytes` must be produced by [`to_bytes`] of `T` and properly aligned.
+pub unsafe fn access_unchecked<T: Archive>(bytes: &[u8]) -> &T::Archived {
+    let pos = bytes.len() - std::mem::size_of::<T::Archived>();
+    &*(bytes.as_ptr().add(pos) as *const T::Archived)
+}
+
+unsafe impl<T: RealQuickSer> Archive for T {
+    type Archived = T;
+    type Resolver = ();
+
+    fn archive_deps(&self, _writer: &mut ArchiveWriter) -> Self::Resolver {}
+
+    unsafe fn resolve(&self, _pos: usize, _resolver: Self::Resolver) -> Self::Archived {
+        let () = T::LAYOUT_CHECK;
+        *self
+    }
+
+    fn verify(buffer: &[u8], pos: usize) -> Result<(), DeError> {
+        if !T::is_valid(&buffer[pos..pos + std::mem::size_of::<T>()]) {
+            return Err(DeError::InvalidValue { offset: pos });
+        }
+        Ok(())
+    }
+}
+
+#[repr(C)]
+pub struct ArchivedVec<T> {
+    offset: i64,
+    len: u64,
+    marker: PhantomData<T>,
+}
+
+impl<T> ArchivedVec<T> {
+    pub fn as_slice(&self) -> &[T] {
+        unsafe {
+            let data = (self as *const Self as *const u8).offset(self.offset as isize);
+            std::slice::from_raw_parts(data as *const T, self.len as usize)
+        }
+    }
+}
+
+impl<T> Deref for ArchivedVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for ArchivedVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+unsafe impl<T: RealQuickSer> Archive for Vec<T> {
+    type Archived = ArchivedVec<T>;
+    type Resolver = usize;
+
+    fn archive_deps(&self, writer: &mut ArchiveWriter) -> Self::Resolver {
+        let () = T::LAYOUT_CHECK;
+        writer.align(std::mem::align_of::<T>());
+        writer.write_bytes(unsafe {
+            std::slice::from_raw_parts(
+                self.as_ptr() as *const u8,
+                self.len() * std::mem::size_of::<T>()
+            )
+        })
+    }
+
+    unsafe fn resolve(&self, pos: usize, data: Self::Resolver) -> Self::Archived {
+        ArchivedVec {
+            offset: data as i64 - pos as i64,
+            len: self.len() as u64,
+            marker: PhantomData,
+        }
+    }
+
+    fn verify(buffer: &[u8], pos: usize) -> Result<(), DeError> {
+        let bytes = verify_slice(buffer, pos, std::mem::size_of::<T>(), std::mem::align_of::<T>())?;
+        let size = std::mem::size_of::<T>();
+        if size == 0 {
+            let mut len = [0; 8];
+            len.copy_from_slice(&buffer[pos + 8..pos + 16]);
+            if u64::from_ne_bytes(len) != 0 && !T::is_valid(&[]) {
+                return Err(DeError::InvalidValue { offset: pos });
+            }
+        } else {
+            let start = bytes.as_ptr() as usize - buffer.as_ptr() as usize;
+            if let Some(i) = bytes.chunks_exact(size).position(|b| !T::is_valid(b)) {
+                return Err(DeError::InvalidValue { offset: start + i * size });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[repr(transparent)]
+pub struct ArchivedString(ArchivedVec<u8>);
+
+impl ArchivedString {
+    pub fn as_str(&self) -> &str {
+        unsafe { std::str::from_utf8_unchecked(self.0.as_slice()) }
+    }
+}
+
+impl Deref for ArchivedString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::fmt::Debug for ArchivedString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+unsafe impl Archive for String {
+    type Archived = ArchivedString;
+    type Resolver = usize;
+
+    fn archive_deps(&self, writer: &mut ArchiveWriter) -> Self::Resolver {
+        writer.write_bytes(self.as_bytes())
+    }
+
+    unsafe fn resolve(&self, pos: usize, data: Self::Resolver) -> Self::Archived {
+        ArchivedString(ArchivedVec {
+            offset: data as i64 - pos as i64,
+            len: self.len() as u64,
+            marker: PhantomData,
+        })
+    }
+
+    fn verify(buffer: &[u8], pos: usize) -> Result<(), DeError> {
+        let bytes = verify_slice(buffer, pos, 1, 1)?;
+        let start = bytes.as_ptr() as usize - buffer.as_ptr() as usize;
+        std::str::from_utf8(bytes)
+            .map(drop)
+            .map_err(|err| DeError::InvalidUtf8 { offset: start + err.valid_up_to() })
+    }
+}
+
+/// Checks that the [`ArchivedVec`] at `pos` points to elements inside `buffer`.
+fn verify_slice(buffer: &[u8], pos: usize, size: usize, align: usize) -> Result<&[u8], DeError> {
+    let mut offset = [0; 8];
+    offset.copy_from_slice(&buffer[pos..pos + 8]);
+    let mut len = [0; 8];
+    len.copy_from_slice(&buffer[pos + 8..pos + 16]);
+
+    let start = isize::try_from(i64::from_ne_bytes(offset)).ok()
+        .and_then(|offset| pos.checked_add_signed(offset))
+        .ok_or(DeError::InvalidValue { offset: pos })?;
+    let len = usize::try_from(u64::from_ne_bytes(len)).ok()
+        .and_then(|len| len.checked_mul(size))
+        .ok_or(DeError::LengthOverflow { offset: pos + 8 })?;
+    let bytes = start.checked_add(len)
+        .and_then(|end| buffer.get(start..end))
+        .ok_or(DeError::UnexpectedEnd { offset: pos })?;
+    if bytes.as_ptr() as usize % align != 0 {
+        return Err(DeError::Misaligned { offset: start });
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,167 @@
+//! Self-describing container around a single [`QuickSer`] value.
+//!
+//! A frame is laid out as follows, all integers are little-endian:
+//!
+//! | field         | size |
+//! |---------------|------|
+//! | magic `QPRC`  | 4    |
+//! | format        | 2    |
+//! | flags         | 1    |
+//! | schema hash   | 8    |
+//! | payload len   | 8    |
+//! | payload       | len  |
+//! | crc32         | 4    |
+//!
+//! The checksum covers everything that precedes it.
+
+use std::fmt;
+
+use crate::{limits::{self, Limits}, take, DeError, QuickSer, PORTABLE};
+
+pub const MAGIC: [u8; 4] = *b"QPRC";
+
+/// Revision of the wire format, bumped whenever encoding of built-in types changes.
+pub const FORMAT_VERSION: u16 = 1;
+
+const FLAG_PORTABLE: u8 = 1 << 0;
+
+const HEADER_LEN: usize = 4 + 2 + 1 + 8 + 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    BadMagic,
+    UnsupportedFormat { version: u16 },
+    FlagsMismatch { expected: u8, found: u8 },
+    TypeMismatch { expected: u64, found: u64 },
+    ChecksumMismatch { expected: u32, found: u32 },
+    LengthMismatch { expected: usize, found: usize },
+    Decode(DeError),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::BadMagic => write!(f, "frame does not start with the magic number"),
+            FrameError::UnsupportedFormat { version } => 
+                write!(f, "unsupported frame format version {}", version),
+            FrameError::FlagsMismatch { expected, found } => 
+                write!(f, "frame flags {:#04x} do not match {:#04x}", found, expected),
+            FrameError::TypeMismatch { expected, found } => 
+                write!(f, "frame holds type {:#018x}, expected {:#018x}", found, expected),
+            FrameError::ChecksumMismatch { expected, found } => 
+                write!(f, "frame checksum {:#010x} does not match {:#010x}", found, expected),
+            FrameError::LengthMismatch { expected, found } => 
+                write!(f, "frame payload is {} bytes long but {} were decoded", expected, found),
+            FrameError::Decode(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FrameError::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DeError> for FrameError {
+    fn from(err: DeError) -> Self {
+        FrameError::Decode(err)
+    }
+}
+
+pub fn write<T: QuickSer>(value: &T, buffer: &mut Vec<u8>) {
+    buffer.reserve(HEADER_LEN + value.ser_len() + 4);
+    let start = buffer.len();
+    buffer.extend_from_slice(&MAGIC);
+    buffer.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    buffer.push(flags());
+    buffer.extend_from_slice(&T::SCHEMA_HASH.to_le_bytes());
+    buffer.extend_from_slice(&[0; 8]);
+
+    let payload_start = buffer.len();
+    value.ser(buffer);
+    let payload_len = (buffer.len() - payload_start) as u64;
+    buffer[payload_start - 8..payload_start].copy_from_slice(&payload_len.to_le_bytes());
+
+    let checksum = crc32(&buffer[start..]);
+    buffer.extend_from_slice(&checksum.to_le_bytes());
+}
+
+pub fn read<T: QuickSer>(progress: &mut usize, buffer: &[u8]) -> Result<T, FrameError> {
+    read_limited(progress, buffer, Limits::default())
+}
+
+/// Same as [`read`] but decodes the payload under `limits`.
+pub fn read_limited<T: QuickSer>(progress: &mut usize, buffer: &[u8], limits: Limits) -> Result<T, FrameError> {
+    let start = *progress;
+    let header = take(progress, buffer, HEADER_LEN)?;
+
+    if header[..4] != MAGIC {
+        return Err(FrameError::BadMagic);
+    }
+
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != FORMAT_VERSION {
+        return Err(FrameError::UnsupportedFormat { version });
+    }
+
+    if header[6] != flags() {
+        return Err(FrameError::FlagsMismatch { expected: flags(), found: header[6] });
+    }
+
+    let found = u64::from_le_bytes(header[7..15].try_into().unwrap());
+    let expected = T::SCHEMA_HASH;
+    if found != expected {
+        return Err(FrameError::TypeMismatch { expected, found });
+    }
+
+    let payload_offset = *progress;
+    let payload_len = usize::try_from(u64::from_le_bytes(header[15..23].try_into().unwrap()))
+        .map_err(|_| DeError::LengthOverflow { offset: start + 15 })?;
+    let payload = take(progress, buffer, payload_len)?;
+    let checked = &buffer[start..*progress];
+    let found = u32::from_le_bytes(take(progress, buffer, 4)?.try_into().unwrap());
+    let expected = crc32(checked);
+    if found != expected {
+        return Err(FrameError::ChecksumMismatch { expected, found });
+    }
+
+    let mut payload_progress = payload_offset;
+    let value = limits::try_de_ser(&mut payload_progress, &buffer[..payload_offset + payload.len()], limits)?;
+    let decoded = payload_progress - payload_offset;
+    if decoded != payload_len {
+        return Err(FrameError::LengthMismatch { expected: payload_len, found: decoded });
+    }
+
+    Ok(value)
+}
+
+fn flags() -> u8 {
+    if PORTABLE { FLAG_PORTABLE } else { 0 }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE 802.3), the same checksum used by zip and png.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
@@ -0,0 +1,90 @@
+//! Saving and loading single [`QuickSer`] values as [`frame`]d files.
+//!
+//! With the `mmap` feature files are memory mapped on load instead of being read
+//! into a buffer. Since [`save`] replaces files by renaming, loading a file that
+//! is being saved concurrently still sees either the old or the new contents.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{frame, limits::Limits, QuickSer};
+
+/// Writes `value` into a temporary file next to `path` and renames it over `path`,
+/// so readers never observe a partially written file.
+pub fn save<T: QuickSer>(path: impl AsRef<Path>, value: &T) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut buffer = Vec::new();
+    frame::write(value, &mut buffer);
+
+    let temp = temp_path(path)?;
+    let result = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(&buffer)?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result?;
+    sync_parent(path)
+}
+
+/// Makes the rename durable, without this a crash can leave the directory 
+/// pointing to the old file even though the new one was synced.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Loads a value stored by [`save`], corrupted files and files holding
+/// a different type fail with [`io::ErrorKind::InvalidData`].
+pub fn load<T: QuickSer>(path: impl AsRef<Path>) -> io::Result<T> {
+    load_limited(path, Limits::default())
+}
+
+/// Same as [`load`] but decodes the value under `limits`.
+pub fn load_limited<T: QuickSer>(path: impl AsRef<Path>, limits: Limits) -> io::Result<T> {
+    let file = File::open(path)?;
+
+    #[cfg(feature = "mmap")]
+    let bytes = unsafe { memmap2::Mmap::map(&file)? };
+    #[cfg(not(feature = "mmap"))]
+    let bytes = {
+        let mut bytes = Vec::new();
+        io::Read::read_to_end(&mut &file, &mut bytes)?;
+        bytes
+    };
+
+    let mut progress = 0;
+    let value = frame::read_limited::<T>(&mut progress, &bytes, limits)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if progress != bytes.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "trailing bytes after the frame"));
+    }
+    Ok(value)
+}
+
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path does not name a file"))?;
+    let mut temp = std::ffi::OsString::from(".");
+    temp.push(name);
+    temp.push(format!(".{}.{}.tmp", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    Ok(path.with_file_name(temp))
+}
@@ -0,0 +1,585 @@
+//! [`QuickSer`] and [`RealQuickSer`] impls for the rest of the standard library.
+//!
+//! Smart pointers are encoded as the value they point to and share its schema hash,
+//! `Box<str>` and `Box<[T]>` (and the `Rc`, `Arc` and `Cow` equivalents) are
+//! encoded as `String` and `Vec<T>`.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, HashSet, LinkedList, VecDeque},
+    ffi::OsString,
+    hash::{BuildHasher, Hash},
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize,
+        NonZeroU128, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, Wrapping,
+    },
+    ops::{Range, RangeInclusive},
+    path::PathBuf,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    gen_quick_copy, items_len, nested, read_items_len, schema_combine, schema_hash,
+    ser_items, write_len,
+    DeError, QuickSer, QuickSink, QuickSource, QuickVarint, RealQuickSer,
+};
+
+fn ser_iter<'a, T: QuickSer + 'a, S: QuickSink>(
+    items: impl ExactSizeIterator<Item = &'a T>,
+    buffer: &mut S,
+) {
+    write_len(items.len(), buffer);
+    for item in items {
+        item.ser(buffer);
+    }
+}
+
+fn iter_len<'a, T: QuickSer + 'a>(items: impl ExactSizeIterator<Item = &'a T>) -> usize {
+    items.len().varint_len() + items.map(T::ser_len).sum::<usize>()
+}
+
+fn de_ser_iter<T: QuickSer, C: FromIterator<T>, S: QuickSource>(source: &mut S) -> Result<C, DeError> {
+    let len = read_items_len::<T, _>(source)?;
+    nested(source, |source| (0..len).map(|_| T::try_de_ser_from(source)).collect())
+}
+
+/// Decodes entries of a map or set, `insert` returns false for a duplicate key, 
+/// which strict sources reject.
+fn de_ser_unique<E, C: Default, S: QuickSource>(
+    source: &mut S,
+    mut de_ser: impl FnMut(&mut S) -> Result<E, DeError>,
+    mut insert: impl FnMut(&mut C, E) -> bool,
+) -> Result<C, DeError> {
+    let len = read_items_len::<E, _>(source)?;
+    nested(source, |source| {
+        let mut result = C::default();
+        for _ in 0..len {
+            let offset = source.offset();
+            let entry = de_ser(source)?;
+            if !insert(&mut result, entry) && source.strict() {
+                return Err(DeError::DuplicateKey { offset });
+            }
+        }
+        Ok(result)
+    })
+}
+
+impl<T: QuickSer + Eq + Hash, H: BuildHasher + Default> QuickSer for HashSet<T, H> {
+    const SCHEMA_HASH: u64 = schema_combine(schema_hash("HashSet"), T::SCHEMA_HASH);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        ser_iter(self.iter(), buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        iter_len(self.iter())
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        de_ser_unique(source, T::try_de_ser_from, HashSet::insert)
+    }
+}
+
+impl<T: QuickSer + Ord> QuickSer for BTreeSet<T> {
+    const SCHEMA_HASH: u64 = schema_combine(schema_hash("BTreeSet"), T::SCHEMA_HASH);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        ser_iter(self.iter(), buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        iter_len(self.iter())
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        de_ser_unique(source, T::try_de_ser_from, BTreeSet::insert)
+    }
+}
+
+impl<T: QuickSer> QuickSer for LinkedList<T> {
+    const SCHEMA_HASH: u64 = schema_combine(schema_hash("LinkedList"), T::SCHEMA_HASH);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        ser_iter(self.iter(), buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        iter_len(self.iter())
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        de_ser_iter(source)
+    }
+}
+
+impl<K: QuickSer + Ord, V: QuickSer> QuickSer for BTreeMap<K, V> {
+    const SCHEMA_HASH: u64 = schema_combine(
+        schema_combine(schema_hash("BTreeMap"), K::SCHEMA_HASH),
+        V::SCHEMA_HASH,
+    );
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        write_len(self.len(), buffer);
+        for (k, v) in self.iter() {
+            k.ser(buffer);
+            v.ser(buffer);
+        }
+    }
+
+    fn ser_len(&self) -> usize {
+        self.len().varint_len() + self.iter().map(|(k, v)| k.ser_len() + v.ser_len()).sum::<usize>()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        de_ser_unique(
+            source,
+            |source| Ok((K::try_de_ser_from(source)?, V::try_de_ser_from(source)?)),
+            |map: &mut BTreeMap<K, V>, (k, v)| map.insert(k, v).is_none(),
+        )
+    }
+}
+
+impl<T: QuickSer> QuickSer for VecDeque<T> {
+    const SCHEMA_HASH: u64 = schema_combine(schema_hash("VecDeque"), T::SCHEMA_HASH);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        write_len(self.len(), buffer);
+        let (front, back) = self.as_slices();
+        ser_items(front, buffer);
+        ser_items(back, buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        let (front, back) = self.as_slices();
+        self.len().varint_len() + items_len(front) + items_len(back)
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        Vec::try_de_ser_from(source).map(VecDeque::from)
+    }
+}
+
+/// Elements are written in the internal order of the heap, so decoding
+/// does not reorder anything.
+impl<T: QuickSer + Ord> QuickSer for BinaryHeap<T> {
+    const SCHEMA_HASH: u64 = schema_combine(schema_hash("BinaryHeap"), T::SCHEMA_HASH);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        write_len(self.len(), buffer);
+        ser_items(self.as_slice(), buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        self.len().varint_len() + items_len(self.as_slice())
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        Vec::try_de_ser_from(source).map(BinaryHeap::from)
+    }
+}
+
+const fn array_schema(element: u64, len: usize) -> u64 {
+    schema_combine(schema_combine(schema_hash("[T; N]"), element), len as u64)
+}
+
+fn de_ser_array<T, const N: usize>(
+    mut de_ser: impl FnMut() -> Result<T, DeError>,
+) -> Result<[T; N], DeError> {
+    let mut items = Vec::with_capacity(N);
+    for _ in 0..N {
+        items.push(de_ser()?);
+    }
+    Ok(items.try_into().unwrap_or_else(|_| unreachable!()))
+}
+
+/// Arrays of quick elements are copied at once, others element by element.
+impl<T: QuickSer, const N: usize> QuickSer for [T; N] {
+    const QUICK: bool = T::QUICK;
+    const SCHEMA_HASH: u64 = array_schema(T::SCHEMA_HASH, N);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        ser_items(self, buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        items_len(self)
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        if !T::QUICK {
+            return de_ser_array(|| T::try_de_ser_from(source));
+        }
+
+        let offset = source.offset();
+        let bytes = source.take(std::mem::size_of::<Self>())?;
+        let size = std::mem::size_of::<T>();
+        if size == 0 {
+            if N != 0 && !T::is_valid_quick(&[]) {
+                return Err(DeError::InvalidValue { offset });
+            }
+        } else if let Some(i) = bytes.chunks_exact(size).position(|b| !T::is_valid_quick(b)) {
+            return Err(DeError::InvalidValue { offset: offset + i * size });
+        }
+        Ok(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const Self) })
+    }
+
+    fn is_valid_quick(bytes: &[u8]) -> bool {
+        match std::mem::size_of::<T>() {
+            0 => N == 0 || T::is_valid_quick(&[]),
+            size => bytes.chunks_exact(size).all(T::is_valid_quick),
+        }
+    }
+}
+
+impl<T: RealQuickSer, const N: usize> RealQuickSer for [T; N] {
+    const LAYOUT_CHECK: () = T::LAYOUT_CHECK;
+    const PORTABLE_LAYOUT: bool = T::PORTABLE_LAYOUT;
+    const QUICK_SCHEMA_HASH: u64 = array_schema(T::QUICK_SCHEMA_HASH, N);
+
+    fn is_valid(bytes: &[u8]) -> bool {
+        match std::mem::size_of::<T>() {
+            0 => N == 0 || T::is_valid(&[]),
+            size => bytes.chunks_exact(size).all(T::is_valid),
+        }
+    }
+
+    fn ser_portable<S: QuickSink>(&self, buffer: &mut S) {
+        for item in self {
+            item.ser_portable(buffer);
+        }
+    }
+
+    fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        de_ser_array(|| T::try_de_ser_portable(source))
+    }
+}
+
+macro_rules! impl_traits_for_pointers {
+    ($($pointer:ident),*) => {
+        $(
+            impl<T: QuickSer> QuickSer for $pointer<T> {
+                const SCHEMA_HASH: u64 = T::SCHEMA_HASH;
+
+                fn ser<S: QuickSink>(&self, buffer: &mut S) {
+                    (**self).ser(buffer);
+                }
+
+                fn ser_len(&self) -> usize {
+                    (**self).ser_len()
+                }
+
+                fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+                    nested(source, T::try_de_ser_from).map($pointer::new)
+                }
+            }
+
+            impl QuickSer for $pointer<str> {
+                const SCHEMA_HASH: u64 = String::SCHEMA_HASH;
+
+                fn ser<S: QuickSink>(&self, buffer: &mut S) {
+                    write_len(self.len(), buffer);
+                    buffer.write(self.as_bytes());
+                }
+
+                fn ser_len(&self) -> usize {
+                    self.len().varint_len() + self.len()
+                }
+
+                fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+                    String::try_de_ser_from(source).map(Into::into)
+                }
+            }
+
+            impl<T: QuickSer> QuickSer for $pointer<[T]> {
+                const SCHEMA_HASH: u64 = Vec::<T>::SCHEMA_HASH;
+
+                fn ser<S: QuickSink>(&self, buffer: &mut S) {
+                    write_len(self.len(), buffer);
+                    ser_items(self, buffer);
+                }
+
+                fn ser_len(&self) -> usize {
+                    self.len().varint_len() + items_len(self)
+                }
+
+                fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+                    Vec::try_de_ser_from(source).map(Into::into)
+                }
+            }
+        )*
+    };
+}
+
+impl_traits_for_pointers!(Box, Rc, Arc);
+
+impl<T: QuickSer + Clone> QuickSer for Cow<'_, T> {
+    const SCHEMA_HASH: u64 = T::SCHEMA_HASH;
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        (**self).ser(buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        (**self).ser_len()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        T::try_de_ser_from(source).map(Cow::Owned)
+    }
+}
+
+impl QuickSer for Cow<'_, str> {
+    const SCHEMA_HASH: u64 = String::SCHEMA_HASH;
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        write_len(self.len(), buffer);
+        buffer.write(self.as_bytes());
+    }
+
+    fn ser_len(&self) -> usize {
+        self.len().varint_len() + self.len()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        String::try_de_ser_from(source).map(Cow::Owned)
+    }
+}
+
+impl<T: QuickSer + Clone> QuickSer for Cow<'_, [T]> {
+    const SCHEMA_HASH: u64 = Vec::<T>::SCHEMA_HASH;
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        write_len(self.len(), buffer);
+        ser_items(self, buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        self.len().varint_len() + items_len(self)
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        Vec::try_de_ser_from(source).map(Cow::Owned)
+    }
+}
+
+impl<T: QuickSer, E: QuickSer> QuickSer for Result<T, E> {
+    const SCHEMA_HASH: u64 = schema_combine(
+        schema_combine(schema_hash("Result"), T::SCHEMA_HASH),
+        E::SCHEMA_HASH,
+    );
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        match self {
+            Ok(t) => {
+                buffer.write(&[0]);
+                t.ser(buffer);
+            },
+            Err(e) => {
+                buffer.write(&[1]);
+                e.ser(buffer);
+            },
+        }
+    }
+
+    fn ser_len(&self) -> usize {
+        1 + match self {
+            Ok(t) => t.ser_len(),
+            Err(e) => e.ser_len(),
+        }
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let offset = source.offset();
+        match source.take(1)?[0] {
+            0 => Ok(Ok(T::try_de_ser_from(source)?)),
+            1 => Ok(Err(E::try_de_ser_from(source)?)),
+            _ => Err(DeError::InvalidTag { offset }),
+        }
+    }
+}
+
+impl<T: QuickSer> QuickSer for Range<T> {
+    const SCHEMA_HASH: u64 = schema_combine(schema_hash("Range"), T::SCHEMA_HASH);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        self.start.ser(buffer);
+        self.end.ser(buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        self.start.ser_len() + self.end.ser_len()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        Ok(T::try_de_ser_from(source)?..T::try_de_ser_from(source)?)
+    }
+}
+
+impl<T: QuickSer> QuickSer for RangeInclusive<T> {
+    const SCHEMA_HASH: u64 = schema_combine(schema_hash("RangeInclusive"), T::SCHEMA_HASH);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        self.start().ser(buffer);
+        self.end().ser(buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        self.start().ser_len() + self.end().ser_len()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        Ok(T::try_de_ser_from(source)?..=T::try_de_ser_from(source)?)
+    }
+}
+
+impl QuickSer for Duration {
+    const SCHEMA_HASH: u64 = schema_hash("Duration");
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        self.as_secs().ser(buffer);
+        self.subsec_nanos().ser(buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        0u64.ser_len() + 0u32.ser_len()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let offset = source.offset();
+        let secs = u64::try_de_ser_from(source)?;
+        let nanos = u32::try_de_ser_from(source)?;
+        if nanos >= 1_000_000_000 {
+            return Err(DeError::InvalidValue { offset });
+        }
+        Ok(Duration::new(secs, nanos))
+    }
+}
+
+/// Raw bytes on unix, elsewhere the (lossy) UTF-8 form is written and
+/// only valid UTF-8 is accepted.
+impl QuickSer for OsString {
+    const SCHEMA_HASH: u64 = schema_hash("OsString");
+
+    #[cfg(unix)]
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        use std::os::unix::ffi::OsStrExt;
+        let bytes = self.as_bytes();
+        write_len(bytes.len(), buffer);
+        buffer.write(bytes);
+    }
+
+    #[cfg(not(unix))]
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        let str = self.to_string_lossy();
+        write_len(str.len(), buffer);
+        buffer.write(str.as_bytes());
+    }
+
+    fn ser_len(&self) -> usize {
+        #[cfg(unix)]
+        let len = std::os::unix::ffi::OsStrExt::as_bytes(self.as_os_str()).len();
+        #[cfg(not(unix))]
+        let len = self.to_string_lossy().len();
+        len.varint_len() + len
+    }
+
+    #[cfg(unix)]
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        use std::os::unix::ffi::OsStringExt;
+        Vec::try_de_ser_from(source).map(OsString::from_vec)
+    }
+
+    #[cfg(not(unix))]
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        String::try_de_ser_from(source).map(OsString::from)
+    }
+}
+
+impl QuickSer for PathBuf {
+    const SCHEMA_HASH: u64 = schema_hash("PathBuf");
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        // avoids cloning into an `OsString` on unix
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let bytes = self.as_os_str().as_bytes();
+            write_len(bytes.len(), buffer);
+            buffer.write(bytes);
+        }
+        #[cfg(not(unix))]
+        self.as_os_str().to_owned().ser(buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        #[cfg(unix)]
+        let len = std::os::unix::ffi::OsStrExt::as_bytes(self.as_os_str()).len();
+        #[cfg(not(unix))]
+        let len = self.to_string_lossy().len();
+        len.varint_len() + len
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        OsString::try_de_ser_from(source).map(PathBuf::from)
+    }
+}
+
+macro_rules! impl_traits_for_non_zero {
+    ($($non_zero:ident($integer:ty)),*) => {
+        $(
+            impl RealQuickSer for $non_zero {
+                const PORTABLE_LAYOUT: bool = <$integer>::PORTABLE_LAYOUT;
+                const QUICK_SCHEMA_HASH: u64 = schema_hash(stringify!($non_zero));
+
+                fn is_valid(bytes: &[u8]) -> bool {
+                    bytes.iter().any(|&b| b != 0)
+                }
+
+                fn ser_portable<S: QuickSink>(&self, buffer: &mut S) {
+                    self.get().ser_portable(buffer);
+                }
+
+                fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+                    let offset = source.offset();
+                    $non_zero::new(<$integer>::try_de_ser_portable(source)?)
+                        .ok_or(DeError::InvalidValue { offset })
+                }
+            }
+
+            impl QuickSer for $non_zero {
+                gen_quick_copy!();
+            }
+        )*
+    };
+}
+
+impl_traits_for_non_zero!(
+    NonZeroU8(u8), NonZeroU16(u16), NonZeroU32(u32), NonZeroU64(u64),
+    NonZeroU128(u128), NonZeroUsize(usize),
+    NonZeroI8(i8), NonZeroI16(i16), NonZeroI32(i32), NonZeroI64(i64),
+    NonZeroI128(i128), NonZeroIsize(isize)
+);
+
+impl<T: RealQuickSer> RealQuickSer for Wrapping<T> {
+    const LAYOUT_CHECK: () = T::LAYOUT_CHECK;
+    const PORTABLE_LAYOUT: bool = T::PORTABLE_LAYOUT;
+    const QUICK_SCHEMA_HASH: u64 = schema_combine(schema_hash("Wrapping"), T::QUICK_SCHEMA_HASH);
+
+    fn is_valid(bytes: &[u8]) -> bool {
+        T::is_valid(bytes)
+    }
+
+    fn ser_portable<S: QuickSink>(&self, buffer: &mut S) {
+        self.0.ser_portable(buffer);
+    }
+
+    fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        T::try_de_ser_portable(source).map(Wrapping)
+    }
+}
+
+impl<T: RealQuickSer> QuickSer for Wrapping<T> {
+    gen_quick_copy!();
+}
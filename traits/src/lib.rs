@@ -1,196 +1,1104 @@
-use std::{marker::PhantomData, time::SystemTime, collections::HashMap, hash::Hash};
+use std::{marker::PhantomData, time::{SystemTime, Duration, UNIX_EPOCH}, collections::HashMap, hash::{BuildHasher, Hash}, fmt};
 
+pub mod archive;
+pub mod frame;
+pub mod fs;
+mod impls;
+pub mod limits;
+pub mod sink;
+pub mod sorted;
+pub mod stream;
 
+/// # Migrating from 0.1
+///
+/// `SCHEMA_HASH` has no default, hand written impls have to provide one, built 
+/// with [`schema_hash`] and [`schema_combine`]. `ser` writes into any [`QuickSink`] 
+/// and impls decode in `try_de_ser_from`, reading from a [`QuickSource`] and reporting
+/// malformed input, `de_ser` is provided on top of it.
 pub trait QuickSer where Self: Sized {
     const QUICK: bool = false;
 
-    fn ser(&self, buffer: &mut Vec<u8>);
+    /// Fingerprint of the encoding of `Self`, changes whenever the name, fields, 
+    /// field types or variants of the type (or any nested type) change.
+    const SCHEMA_HASH: u64;
 
-    fn de_ser(progress: &mut usize, buffer: &[u8]) -> Self;
-}
+    fn ser<S: QuickSink>(&self, buffer: &mut S);
 
-pub trait RealQuickSer: Copy {}
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError>;
 
-impl<T> RealQuickSer for PhantomData<T> {}
+    /// Exact number of bytes [`QuickSer::ser`] writes.
+    fn ser_len(&self) -> usize {
+        let mut counter = sink::CountingSink::new();
+        self.ser(&mut counter);
+        counter.count()
+    }
 
-impl QuickSer for String {
-    fn ser(&self, buffer: &mut Vec<u8>) {
-        self.len().ser(buffer);
-        buffer.extend_from_slice(self.as_bytes());
+    /// Serializes after reserving [`QuickSer::ser_len`] bytes up front, so the whole 
+    /// value costs at most one reallocation instead of one per growing field.
+    fn ser_reserved<S: QuickSink>(&self, buffer: &mut S) {
+        buffer.reserve(self.ser_len());
+        self.ser(buffer);
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.ser_reserved(&mut buffer);
+        buffer
+    }
+
+    fn try_de_ser(progress: &mut usize, buffer: &[u8]) -> Result<Self, DeError> {
+        let mut source = SliceSource::new(buffer, *progress);
+        let result = Self::try_de_ser_from(&mut source);
+        *progress = source.offset();
+        result
     }
 
     fn de_ser(progress: &mut usize, buffer: &[u8]) -> Self {
-        let len = usize::de_ser(progress, buffer);
-        let mut result = String::with_capacity(len);
-        result.push_str(unsafe { 
-            std::str::from_utf8_unchecked(&buffer[*progress..*progress + len]) 
-        });
-        *progress += len;
+        Self::try_de_ser(progress, buffer).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    #[doc(hidden)]
+    fn is_valid_quick(_bytes: &[u8]) -> bool {
+        true
+    }
+
+    /// Same as [`QuickSer::try_de_ser`] but skips validation that is only needed 
+    /// for untrusted input, such as utf-8 checks on strings.
+    /// 
+    /// # Safety
+    /// 
+    /// `buffer` must contain data produced by [`QuickSer::ser`] of the same type.
+    unsafe fn try_de_ser_trusted(progress: &mut usize, buffer: &[u8]) -> Result<Self, DeError> {
+        let mut source = SliceSource::new_trusted(buffer, *progress);
+        let result = Self::try_de_ser_from(&mut source);
+        *progress = source.offset();
+        result
+    }
+}
+
+/// Destination of [`QuickSer::ser`].
+pub trait QuickSink {
+    fn write(&mut self, bytes: &[u8]);
+
+    /// Hints that at least `additional` more bytes are about to be written.
+    fn reserve(&mut self, _additional: usize) {}
+}
+
+impl QuickSink for Vec<u8> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+}
+
+impl<S: QuickSink + ?Sized> QuickSink for &mut S {
+    fn write(&mut self, bytes: &[u8]) {
+        (**self).write(bytes);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        (**self).reserve(additional);
+    }
+}
+
+/// Input of [`QuickSer::try_de_ser_from`].
+/// 
+/// # Safety
+/// 
+/// `take` must return exactly `len` bytes and `trusted` may only return true 
+/// if the input is known to be produced by [`QuickSer::ser`] of the decoded type.
+pub unsafe trait QuickSource {
+    /// Number of bytes consumed so far, reported by [`DeError`].
+    fn offset(&self) -> usize;
+
+    fn take(&mut self, len: usize) -> Result<&[u8], DeError>;
+
+    fn trusted(&self) -> bool {
+        false
+    }
+
+    /// Called with the declared length of a collection and the size of its elements 
+    /// before anything is allocated for it.
+    fn check_len(&mut self, _len: usize, _size: usize) -> Result<(), DeError> {
+        Ok(())
+    }
+
+    /// Called before decoding the elements of a collection, paired with [`QuickSource::leave`].
+    fn enter(&mut self) -> Result<(), DeError> {
+        Ok(())
+    }
+
+    fn leave(&mut self) {}
+
+    /// Whether maps and sets reject duplicate keys instead of keeping the last one.
+    fn strict(&self) -> bool {
+        false
+    }
+}
+
+unsafe impl<S: QuickSource + ?Sized> QuickSource for &mut S {
+    fn offset(&self) -> usize {
+        (**self).offset()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], DeError> {
+        (**self).take(len)
+    }
+
+    fn trusted(&self) -> bool {
+        (**self).trusted()
+    }
+
+    fn check_len(&mut self, len: usize, size: usize) -> Result<(), DeError> {
+        (**self).check_len(len, size)
+    }
+
+    fn enter(&mut self) -> Result<(), DeError> {
+        (**self).enter()
+    }
+
+    fn leave(&mut self) {
+        (**self).leave()
+    }
+
+    fn strict(&self) -> bool {
+        (**self).strict()
+    }
+}
+
+/// Input of [`QuickDe`], a [`QuickSource`] whose bytes outlive it.
+///
+/// # Safety
+///
+/// Same as [`QuickSource`], `take_borrowed` must return exactly `len` bytes.
+pub unsafe trait BorrowSource<'a>: QuickSource {
+    /// Same as [`QuickSource::take`] but the bytes outlive the source.
+    fn take_borrowed(&mut self, len: usize) -> Result<&'a [u8], DeError>;
+}
+
+pub struct SliceSource<'a> {
+    buffer: &'a [u8],
+    progress: usize,
+    trusted: bool,
+}
+
+impl<'a> SliceSource<'a> {
+    pub fn new(buffer: &'a [u8], progress: usize) -> Self {
+        Self { buffer, progress, trusted: false }
+    }
+
+    /// # Safety
+    /// 
+    /// Same as [`QuickSer::try_de_ser_trusted`].
+    pub unsafe fn new_trusted(buffer: &'a [u8], progress: usize) -> Self {
+        Self { buffer, progress, trusted: true }
+    }
+
+    /// Same as [`QuickSource::take`] but the bytes outlive the source.
+    pub fn take_borrowed(&mut self, len: usize) -> Result<&'a [u8], DeError> {
+        take(&mut self.progress, self.buffer, len)
+    }
+}
+
+unsafe impl QuickSource for SliceSource<'_> {
+    fn offset(&self) -> usize {
+        self.progress
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], DeError> {
+        self.take_borrowed(len)
+    }
+
+    fn trusted(&self) -> bool {
+        self.trusted
+    }
+}
+
+unsafe impl<'a> BorrowSource<'a> for SliceSource<'a> {
+    fn take_borrowed(&mut self, len: usize) -> Result<&'a [u8], DeError> {
+        SliceSource::take_borrowed(self, len)
+    }
+}
+
+/// Decoding that may borrow from the input buffer instead of copying, implemented 
+/// for `&str`, `&[T]` of [`RealQuickSer`] elements and every [`QuickSer`] type.
+/// The encoding of `&str` and `&[T]` is the same as of `String` and `Vec<T>`.
+///
+/// The encoding does not pad anything, so where the elements of a `&[T]` land 
+/// depends on everything encoded before them. Elements aligned to more than one 
+/// byte are only borrowed if their data happens to be aligned in the buffer, 
+/// otherwise decoding fails with [`DeError::Misaligned`]. `&str`, `&[u8]` and 
+/// other single byte elements always work, wider ones are reliable only when the 
+/// preceding fields have a known encoded length, elsewhere decode into `Vec<T>`.
+pub trait QuickDe<'a>: Sized {
+    fn try_de_ser_borrowed_from<S: BorrowSource<'a>>(source: &mut S) -> Result<Self, DeError>;
+
+    fn try_de_ser_borrowed(progress: &mut usize, buffer: &'a [u8]) -> Result<Self, DeError> {
+        let mut source = SliceSource::new(buffer, *progress);
+        let result = Self::try_de_ser_borrowed_from(&mut source);
+        *progress = source.offset();
         result
     }
 }
 
+impl<'a, T: QuickSer> QuickDe<'a> for T {
+    fn try_de_ser_borrowed_from<S: BorrowSource<'a>>(source: &mut S) -> Result<Self, DeError> {
+        T::try_de_ser_from(source)
+    }
+}
+
+impl<'a> QuickDe<'a> for &'a str {
+    fn try_de_ser_borrowed_from<S: BorrowSource<'a>>(source: &mut S) -> Result<Self, DeError> {
+        let len = read_len(source)?;
+        let offset = source.offset();
+        let bytes = source.take_borrowed(len)?;
+        if source.trusted() {
+            return Ok(unsafe { std::str::from_utf8_unchecked(bytes) });
+        }
+        std::str::from_utf8(bytes)
+            .map_err(|err| DeError::InvalidUtf8 { offset: offset + err.valid_up_to() })
+    }
+}
+
+/// Fails with [`DeError::Misaligned`] if the data is not aligned for `T`, see [`QuickDe`].
+impl<'a, T: RealQuickSer> QuickDe<'a> for &'a [T] {
+    fn try_de_ser_borrowed_from<S: BorrowSource<'a>>(source: &mut S) -> Result<Self, DeError> {
+        let () = T::LAYOUT_CHECK;
+        const { 
+            assert!(
+                !PORTABLE || T::PORTABLE_LAYOUT, 
+                "borrowed slices need elements whose encoding matches their memory layout"
+            ) 
+        };
+        let len = read_len(source)?;
+        let size = std::mem::size_of::<T>();
+        let offset = source.offset();
+        if size == 0 {
+            // there are no bytes to check, but an uninhabited type must still be rejected
+            if len != 0 && !T::is_valid(&[]) {
+                return Err(DeError::InvalidValue { offset });
+            }
+            return Ok(unsafe { std::slice::from_raw_parts(std::ptr::NonNull::dangling().as_ptr(), len) });
+        }
+        let true_len = len.checked_mul(size).ok_or(DeError::LengthOverflow { offset })?;
+        let bytes = source.take_borrowed(true_len)?;
+        if bytes.as_ptr() as usize % std::mem::align_of::<T>() != 0 {
+            return Err(DeError::Misaligned { offset });
+        }
+        if let Some(i) = bytes.chunks_exact(size).position(|b| !T::is_valid(b)) {
+            return Err(DeError::InvalidValue { offset: offset + i * size });
+        }
+        Ok(unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const T, len) })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeError {
+    UnexpectedEnd { offset: usize },
+    InvalidTag { offset: usize },
+    InvalidUtf8 { offset: usize },
+    LengthOverflow { offset: usize },
+    InvalidValue { offset: usize },
+    UnsupportedVersion { offset: usize },
+    /// Borrowed data is not aligned for its type within the input buffer.
+    Misaligned { offset: usize },
+    /// The input exceeds [`limits::Limits`] of the source.
+    LimitExceeded { offset: usize },
+    /// A map or set contains the same key twice and the source is strict.
+    DuplicateKey { offset: usize },
+}
+
+impl DeError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            DeError::UnexpectedEnd { offset } 
+            | DeError::InvalidTag { offset } 
+            | DeError::InvalidUtf8 { offset } 
+            | DeError::LengthOverflow { offset } 
+            | DeError::InvalidValue { offset } 
+            | DeError::UnsupportedVersion { offset } 
+            | DeError::Misaligned { offset } 
+            | DeError::LimitExceeded { offset } 
+            | DeError::DuplicateKey { offset } => offset,
+        }
+    }
+}
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeError::UnexpectedEnd { offset } => write!(f, "unexpected end of input at byte {}", offset),
+            DeError::InvalidTag { offset } => write!(f, "invalid enum tag at byte {}", offset),
+            DeError::InvalidUtf8 { offset } => write!(f, "invalid utf-8 at byte {}", offset),
+            DeError::LengthOverflow { offset } => write!(f, "length overflow at byte {}", offset),
+            DeError::InvalidValue { offset } => write!(f, "invalid value at byte {}", offset),
+            DeError::UnsupportedVersion { offset } => write!(f, "unsupported version at byte {}", offset),
+            DeError::Misaligned { offset } => write!(f, "misaligned borrowed data at byte {}", offset),
+            DeError::LimitExceeded { offset } => write!(f, "decode limit exceeded at byte {}", offset),
+            DeError::DuplicateKey { offset } => write!(f, "duplicate key at byte {}", offset),
+        }
+    }
+}
+
+impl std::error::Error for DeError {}
+
+/// FNV-1a hash of `name`, the starting point of every [`QuickSer::SCHEMA_HASH`].
+pub const fn schema_hash(name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Mixes the hash of a nested type into `hash`, order matters.
+pub const fn schema_combine(hash: u64, nested: u64) -> u64 {
+    let bytes = nested.to_le_bytes();
+    let mut hash = hash;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Mixes the size and field offsets of a type encoded as a copy of its memory into `hash`,
+/// since its layout can change while the field types stay the same.
+pub const fn schema_layout(hash: u64, size: usize, offsets: &[usize]) -> u64 {
+    let mut hash = schema_combine(hash, size as u64);
+    let mut i = 0;
+    while i < offsets.len() {
+        hash = schema_combine(hash, offsets[i] as u64);
+        i += 1;
+    }
+    hash
+}
+
+fn take<'a>(progress: &mut usize, buffer: &'a [u8], len: usize) -> Result<&'a [u8], DeError> {
+    let start = *progress;
+    let end = start.checked_add(len).ok_or(DeError::LengthOverflow { offset: start })?;
+    let bytes = buffer.get(start..end).ok_or(DeError::UnexpectedEnd { offset: start })?;
+    *progress = end;
+    Ok(bytes)
+}
+
+fn write_len<S: QuickSink>(len: usize, buffer: &mut S) {
+    len.ser_varint(buffer);
+}
+
+fn read_len<S: QuickSource>(source: &mut S) -> Result<usize, DeError> {
+    usize::try_de_ser_varint(source)
+}
+
+/// Reads the length of a collection of `T` and lets the source check it.
+fn read_items_len<T, S: QuickSource>(source: &mut S) -> Result<usize, DeError> {
+    let len = read_len(source)?;
+    source.check_len(len, std::mem::size_of::<T>())?;
+    Ok(len)
+}
+
+/// Runs `de_ser` between [`QuickSource::enter`] and [`QuickSource::leave`].
+fn nested<S: QuickSource, T>(
+    source: &mut S, 
+    de_ser: impl FnOnce(&mut S) -> Result<T, DeError>,
+) -> Result<T, DeError> {
+    source.enter()?;
+    let result = de_ser(source);
+    source.leave();
+    result
+}
+
+/// Capacity reserved up front for `len` declared elements, so that a corrupted 
+/// length does not allocate more than the input can fill.
+fn initial_capacity<T>(len: usize) -> usize {
+    len.min(BULK_CHUNK / std::mem::size_of::<T>().max(1))
+}
+
+/// Variable length (LEB128) encoding of integers, signed integers are zigzag encoded 
+/// first so that small negative values stay small.
+pub trait QuickVarint: Sized {
+    fn ser_varint<S: QuickSink>(&self, buffer: &mut S);
+
+    fn varint_len(&self) -> usize;
+
+    fn try_de_ser_varint<S: QuickSource>(source: &mut S) -> Result<Self, DeError>;
+}
+
+macro_rules! impl_varint_for_unsigned {
+    ($($unsigned:ty),*) => {
+        $(
+            impl QuickVarint for $unsigned {
+                fn ser_varint<S: QuickSink>(&self, buffer: &mut S) {
+                    let mut bytes = [0; (<$unsigned>::BITS as usize + 6) / 7];
+                    let mut len = 0;
+                    let mut value = *self;
+                    while value >= 0x80 {
+                        bytes[len] = value as u8 | 0x80;
+                        len += 1;
+                        value >>= 7;
+                    }
+                    bytes[len] = value as u8;
+                    buffer.write(&bytes[..len + 1]);
+                }
+
+                fn varint_len(&self) -> usize {
+                    let mut len = 1;
+                    let mut value = *self;
+                    while value >= 0x80 {
+                        len += 1;
+                        value >>= 7;
+                    }
+                    len
+                }
+
+                fn try_de_ser_varint<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+                    let offset = source.offset();
+                    let mut result: $unsigned = 0;
+                    let mut shift = 0;
+                    loop {
+                        let byte = source.take(1)?[0];
+                        let bits = (byte & 0x7F) as $unsigned;
+                        if shift >= <$unsigned>::BITS || (bits << shift) >> shift != bits {
+                            return Err(DeError::InvalidValue { offset });
+                        }
+                        result |= bits << shift;
+                        if byte & 0x80 == 0 {
+                            return Ok(result);
+                        }
+                        shift += 7;
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_varint_for_unsigned!(u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_varint_for_signed {
+    ($($signed:ty => $unsigned:ty),*) => {
+        $(
+            impl QuickVarint for $signed {
+                fn ser_varint<S: QuickSink>(&self, buffer: &mut S) {
+                    let zigzag = ((*self << 1) ^ (*self >> (<$signed>::BITS - 1))) as $unsigned;
+                    zigzag.ser_varint(buffer);
+                }
+
+                fn varint_len(&self) -> usize {
+                    (((*self << 1) ^ (*self >> (<$signed>::BITS - 1))) as $unsigned).varint_len()
+                }
+
+                fn try_de_ser_varint<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+                    let zigzag = <$unsigned>::try_de_ser_varint(source)?;
+                    Ok((zigzag >> 1) as $signed ^ -((zigzag & 1) as $signed))
+                }
+            }
+        )*
+    };
+}
+
+impl_varint_for_signed!(
+    i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize
+);
+
+/// Whether the crate was built with the `portable` feature, in which case all
+/// primitives are encoded as fixed width little-endian regardless of the host.
+pub const PORTABLE: bool = cfg!(feature = "portable");
+
+/// # Migrating from 0.1
+///
+/// [`RealQuickSer`] types no longer implement [`QuickSer`] through a blanket impl, 
+/// since it overlapped with the impls of `Box`, `Rc` and the like. Hand written
+/// impls now need
+///
+/// ```ignore
+/// impl RealQuickSer for X {
+///     const QUICK_SCHEMA_HASH: u64 = schema_hash("X");
+///
+///     fn ser_portable<S: QuickSink>(&self, buffer: &mut S) { /* field by field, little-endian */ }
+///
+///     fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> { /* ... */ }
+/// }
+///
+/// impl QuickSer for X {
+///     gen_quick_copy!();
+/// }
+/// ```
+///
+/// `#[derive(RealQuickSer)]` generates both.
+pub trait RealQuickSer: Copy {
+    /// Fails to evaluate if the layout of `Self` contains padding bytes.
+    #[doc(hidden)]
+    const LAYOUT_CHECK: () = ();
+
+    /// Whether the in-memory representation of `Self` on this host is identical 
+    /// to its portable encoding, allowing bulk copies even in portable mode.
+    const PORTABLE_LAYOUT: bool = false;
+
+    /// Becomes [`QuickSer::SCHEMA_HASH`] through [`gen_quick_copy`].
+    const QUICK_SCHEMA_HASH: u64;
+
+    fn ser_portable<S: QuickSink>(&self, buffer: &mut S);
+
+    fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError>;
+
+    /// Checks whether `bytes`, which are exactly `size_of::<Self>()` long, 
+    /// hold a valid bit pattern of `Self`.
+    fn is_valid(_bytes: &[u8]) -> bool {
+        true
+    }
+}
+
+impl<T> RealQuickSer for PhantomData<T> {
+    const PORTABLE_LAYOUT: bool = true;
+    const QUICK_SCHEMA_HASH: u64 = schema_hash("PhantomData");
+
+    fn ser_portable<S: QuickSink>(&self, _buffer: &mut S) {}
+
+    fn try_de_ser_portable<S: QuickSource>(_source: &mut S) -> Result<Self, DeError> {
+        Ok(PhantomData)
+    }
+}
+
+impl<T> QuickSer for PhantomData<T> {
+    gen_quick_copy!();
+}
+
+impl QuickSer for String {
+    const SCHEMA_HASH: u64 = schema_hash("String");
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        write_len(self.len(), buffer);
+        buffer.write(self.as_bytes());
+    }
+
+    fn ser_len(&self) -> usize {
+        self.len().varint_len() + self.len()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let len = read_items_len::<u8, _>(source)?;
+        let offset = source.offset();
+        let trusted = source.trusted();
+        let bytes = source.take(len)?;
+        if trusted {
+            return Ok(unsafe { std::str::from_utf8_unchecked(bytes) }.to_owned());
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(str) => Ok(str.to_owned()),
+            Err(err) => Err(DeError::InvalidUtf8 { offset: offset + err.valid_up_to() }),
+        }
+    }
+}
+
 impl<T: QuickSer> QuickSer for Option<T> {
-    fn ser(&self, buffer: &mut Vec<u8>) {
+    const SCHEMA_HASH: u64 = schema_combine(schema_hash("Option"), T::SCHEMA_HASH);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
         match self {
             Some(t) => {
-                buffer.push(1);
+                buffer.write(&[1]);
                 t.ser(buffer);
             },
             None => {
-                buffer.push(0);
+                buffer.write(&[0]);
             },
         }
     }
 
-    fn de_ser(progress: &mut usize, buffer: &[u8]) -> Self {
-        match buffer[*progress] {
-            0 => {
-                *progress += 1;
-                None
-            },
-            1 => {
-                *progress += 1;
-                Some(T::de_ser(progress, buffer))
-            },
-            _ => panic!("invalid enum tag"),
+    fn ser_len(&self) -> usize {
+        1 + self.as_ref().map_or(0, T::ser_len)
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let offset = source.offset();
+        match source.take(1)?[0] {
+            0 => Ok(None),
+            1 => Ok(Some(T::try_de_ser_from(source)?)),
+            _ => Err(DeError::InvalidTag { offset }),
         }
     }
 }
 
-impl<K: QuickSer + Eq + Hash, V: QuickSer> QuickSer for HashMap<K, V> {
-    fn ser(&self, buffer: &mut Vec<u8>) {
-        self.len().ser(buffer);
+/// Entries are written in iteration order, see [`sorted`] for a deterministic encoding.
+impl<K: QuickSer + Eq + Hash, V: QuickSer, H: BuildHasher + Default> QuickSer for HashMap<K, V, H> {
+    const SCHEMA_HASH: u64 = schema_combine(
+        schema_combine(schema_hash("HashMap"), K::SCHEMA_HASH), 
+        V::SCHEMA_HASH,
+    );
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        write_len(self.len(), buffer);
         for (k, v) in self.iter() {
             k.ser(buffer);
             v.ser(buffer);
         }
     }
 
-    fn de_ser(progress: &mut usize, buffer: &[u8]) -> Self {
-        let len = usize::de_ser(progress, buffer);
-        let mut result = HashMap::with_capacity(len);
-        for _ in 0..len {
-            let k = K::de_ser(progress, buffer);
-            let v = V::de_ser(progress, buffer);
-            result.insert(k, v);
+    fn ser_len(&self) -> usize {
+        self.len().varint_len() + self.iter().map(|(k, v)| k.ser_len() + v.ser_len()).sum::<usize>()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let len = read_items_len::<(K, V), _>(source)?;
+        nested(source, |source| {
+            let mut result = HashMap::with_capacity_and_hasher(initial_capacity::<(K, V)>(len), H::default());
+            for _ in 0..len {
+                let offset = source.offset();
+                let k = K::try_de_ser_from(source)?;
+                let v = V::try_de_ser_from(source)?;
+                if result.insert(k, v).is_some() && source.strict() {
+                    return Err(DeError::DuplicateKey { offset });
+                }
+            }
+            Ok(result)
+        })
+    }
+}
+
+/// Upper bound of bytes taken from a source at once by bulk copies.
+const BULK_CHUNK: usize = 64 * 1024;
+
+/// Writes `items` without their length, in a single copy if `T` is quick.
+fn ser_items<T: QuickSer, S: QuickSink>(items: &[T], buffer: &mut S) {
+    if T::QUICK {
+        buffer.write(unsafe {
+            std::slice::from_raw_parts(
+                items.as_ptr() as *const u8, 
+                std::mem::size_of_val(items)
+            )
+        });
+    } else {
+        for item in items {
+            item.ser(buffer);
         }
-        result
+    }
+}
+
+fn items_len<T: QuickSer>(items: &[T]) -> usize {
+    if T::QUICK {
+        std::mem::size_of_val(items)
+    } else {
+        items.iter().map(T::ser_len).sum()
     }
 }
 
 impl<T: QuickSer> QuickSer for Vec<T> {
-    fn ser(&self, buffer: &mut Vec<u8>) {
-        if T::QUICK {
-            self.len().ser(buffer);
-            let len = self.len() * std::mem::size_of::<T>();
-            let new_len = len + buffer.len();
-            buffer.reserve(new_len);
-            unsafe {
-                buffer.set_len(new_len);
-                std::ptr::copy_nonoverlapping(
-                    self.as_ptr() as *const u8, 
-                    buffer.as_mut_ptr().offset((buffer.len() - len) as isize), 
-                    len
-                );
-            }
-        } else {
-            self.len().ser(buffer);
-            for item in self {
-                item.ser(buffer);
+    const SCHEMA_HASH: u64 = schema_combine(schema_hash("Vec"), T::SCHEMA_HASH);
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        write_len(self.len(), buffer);
+        ser_items(self, buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        self.len().varint_len() + items_len(self)
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let len = read_items_len::<T, _>(source)?;
+        if !T::QUICK {
+            return nested(source, |source| {
+                let mut result = Vec::with_capacity(initial_capacity::<T>(len));
+                for _ in 0..len {
+                    result.push(T::try_de_ser_from(source)?);
+                }
+                Ok(result)
+            });
+        }
+
+        let size = std::mem::size_of::<T>();
+        let offset = source.offset();
+        len.checked_mul(size).ok_or(DeError::LengthOverflow { offset })?;
+        let mut result = Vec::new();
+        if size == 0 {
+            // there are no bytes to check, but an uninhabited type must still be rejected
+            if len != 0 && !T::is_valid_quick(&[]) {
+                return Err(DeError::InvalidValue { offset });
             }
+            result.reserve(len);
+            unsafe { result.set_len(len) };
+            return Ok(result);
         }
-    }
 
-    fn de_ser(progress: &mut usize, buffer: &[u8]) -> Self {
-        if T::QUICK {
-            let len = usize::de_ser(progress, buffer);
-            let mut result = Vec::with_capacity(len);
-            let true_len = len * std::mem::size_of::<T>();
+        // copied in chunks so that streaming sources never have to buffer 
+        // the whole vector
+        let per_chunk = (BULK_CHUNK / size).max(1);
+        while result.len() < len {
+            let count = (len - result.len()).min(per_chunk);
+            let offset = source.offset();
+            let bytes = source.take(count * size)?;
+            if let Some(i) = bytes.chunks_exact(size).position(|b| !T::is_valid_quick(b)) {
+                return Err(DeError::InvalidValue { offset: offset + i * size });
+            }
+            result.reserve(count);
             unsafe {
-                result.set_len(len);
                 std::ptr::copy_nonoverlapping(
-                    buffer.as_ptr().offset(*progress as isize),
-                    result.as_mut_ptr() as *mut u8,
-                    true_len
+                    bytes.as_ptr(),
+                    result.as_mut_ptr().add(result.len()) as *mut u8,
+                    count * size
                 );
+                result.set_len(result.len() + count);
             }
-            *progress += true_len;
-            result
-        } else {
-            let mut result = Vec::with_capacity(usize::de_ser(progress, buffer));
-            for _ in 0..result.capacity() {
-                result.push(T::de_ser(progress, buffer));
-            }
-            result
         }
+        Ok(result)
     }
 }
 
+/// Implements [`QuickSer`] for a [`RealQuickSer`] type as a plain copy of its bytes,
+/// `#[derive(RealQuickSer)]` expands to `impl QuickSer for T { gen_quick_copy!(); }`.
 #[macro_export]
 macro_rules! gen_quick_copy {
     () => {
-        fn ser(&self, buffer: &mut Vec<u8>) {
-            let size = std::mem::size_of::<Self>();
-            let new_len = buffer.len() + size;
-            buffer.reserve(new_len);
-            unsafe {
-                buffer.set_len(new_len);
-                std::ptr::write(
-                    buffer.as_mut_ptr().offset((buffer.len() - size) as isize) as *mut Self,
-                    self.to_owned()
-                );
+        const QUICK: bool = {
+            let () = <Self as $crate::RealQuickSer>::LAYOUT_CHECK;
+            !$crate::PORTABLE || <Self as $crate::RealQuickSer>::PORTABLE_LAYOUT
+        };
+        const SCHEMA_HASH: u64 = <Self as $crate::RealQuickSer>::QUICK_SCHEMA_HASH;
+
+        fn ser<S: $crate::QuickSink>(&self, buffer: &mut S) {
+            let () = <Self as $crate::RealQuickSer>::LAYOUT_CHECK;
+            if $crate::PORTABLE && !<Self as $crate::RealQuickSer>::PORTABLE_LAYOUT {
+                return $crate::RealQuickSer::ser_portable(self, buffer);
             }
-        }
-    
-        fn de_ser(progress: &mut usize, buffer: &[u8]) -> Self {
-            let size = std::mem::size_of::<Self>();
-            let result = unsafe {
-                std::ptr::read(
-                    buffer.as_ptr().offset(*progress as isize) as *const Self
+            buffer.write(unsafe {
+                std::slice::from_raw_parts(
+                    self as *const Self as *const u8, 
+                    std::mem::size_of::<Self>()
                 )
-            };
-            *progress += size;
-            result
+            });
         }
-    };
-}
 
-impl<T: RealQuickSer> QuickSer for T {
-    const QUICK: bool = true;
+        fn ser_len(&self) -> usize {
+            if $crate::PORTABLE && !<Self as $crate::RealQuickSer>::PORTABLE_LAYOUT {
+                let mut counter = $crate::sink::CountingSink::new();
+                $crate::RealQuickSer::ser_portable(self, &mut counter);
+                return counter.count();
+            }
+            std::mem::size_of::<Self>()
+        }
+    
+        fn try_de_ser_from<S: $crate::QuickSource>(source: &mut S) -> Result<Self, $crate::DeError> {
+            if $crate::PORTABLE && !<Self as $crate::RealQuickSer>::PORTABLE_LAYOUT {
+                return <Self as $crate::RealQuickSer>::try_de_ser_portable(source);
+            }
+            let offset = $crate::QuickSource::offset(source);
+            let bytes = $crate::QuickSource::take(source, std::mem::size_of::<Self>())?;
+            if !<Self as $crate::RealQuickSer>::is_valid(bytes) {
+                return Err($crate::DeError::InvalidValue { offset });
+            }
+            Ok(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const Self) })
+        }
 
-    gen_quick_copy!();
+        fn is_valid_quick(bytes: &[u8]) -> bool {
+            <Self as $crate::RealQuickSer>::is_valid(bytes)
+        }
+    };
 }
 
 macro_rules! impl_traits_for_types {
     ($($integer:ty),*) => {
         $(
-            impl RealQuickSer for $integer {}
+            impl RealQuickSer for $integer {
+                const PORTABLE_LAYOUT: bool = 
+                    cfg!(target_endian = "little") || std::mem::size_of::<$integer>() == 1;
+                const QUICK_SCHEMA_HASH: u64 = schema_hash(stringify!($integer));
+
+                fn ser_portable<S: QuickSink>(&self, buffer: &mut S) {
+                    buffer.write(&self.to_le_bytes());
+                }
+
+                fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+                    let size = std::mem::size_of::<$integer>();
+                    let mut bytes = [0; std::mem::size_of::<$integer>()];
+                    bytes.copy_from_slice(source.take(size)?);
+                    Ok(Self::from_le_bytes(bytes))
+                }
+            }
+
+            impl QuickSer for $integer {
+                gen_quick_copy!();
+            }
         )*
     };
 }
 
 impl_traits_for_types!(
-    u8, u16, u32, u64, u128, usize, 
-    i8, i16, i32, i64, i128, isize,
-    f32, f64, bool, char, SystemTime
+    u8, u16, u32, u64, u128, 
+    i8, i16, i32, i64, i128,
+    f32, f64
 );
 
+impl RealQuickSer for usize {
+    const PORTABLE_LAYOUT: bool = cfg!(all(target_endian = "little", target_pointer_width = "64"));
+    const QUICK_SCHEMA_HASH: u64 = schema_hash("usize");
+
+    fn ser_portable<S: QuickSink>(&self, buffer: &mut S) {
+        (*self as u64).ser_portable(buffer);
+    }
+
+    fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let offset = source.offset();
+        usize::try_from(u64::try_de_ser_portable(source)?)
+            .map_err(|_| DeError::LengthOverflow { offset })
+    }
+}
+
+impl QuickSer for usize {
+    gen_quick_copy!();
+}
+
+impl RealQuickSer for isize {
+    const PORTABLE_LAYOUT: bool = cfg!(all(target_endian = "little", target_pointer_width = "64"));
+    const QUICK_SCHEMA_HASH: u64 = schema_hash("isize");
+
+    fn ser_portable<S: QuickSink>(&self, buffer: &mut S) {
+        (*self as i64).ser_portable(buffer);
+    }
+
+    fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let offset = source.offset();
+        isize::try_from(i64::try_de_ser_portable(source)?)
+            .map_err(|_| DeError::InvalidValue { offset })
+    }
+}
+
+impl QuickSer for isize {
+    gen_quick_copy!();
+}
+
+impl RealQuickSer for bool {
+    const PORTABLE_LAYOUT: bool = true;
+    const QUICK_SCHEMA_HASH: u64 = schema_hash("bool");
+
+    fn is_valid(bytes: &[u8]) -> bool {
+        bytes[0] <= 1
+    }
+
+    fn ser_portable<S: QuickSink>(&self, buffer: &mut S) {
+        buffer.write(&[*self as u8]);
+    }
+
+    fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let offset = source.offset();
+        match source.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DeError::InvalidValue { offset }),
+        }
+    }
+}
+
+impl QuickSer for bool {
+    gen_quick_copy!();
+}
+
+impl RealQuickSer for char {
+    const PORTABLE_LAYOUT: bool = cfg!(target_endian = "little");
+    const QUICK_SCHEMA_HASH: u64 = schema_hash("char");
+
+    fn is_valid(bytes: &[u8]) -> bool {
+        let mut code = [0; 4];
+        code.copy_from_slice(bytes);
+        char::from_u32(u32::from_ne_bytes(code)).is_some()
+    }
+
+    fn ser_portable<S: QuickSink>(&self, buffer: &mut S) {
+        (*self as u32).ser_portable(buffer);
+    }
+
+    fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let offset = source.offset();
+        char::from_u32(u32::try_de_ser_portable(source)?)
+            .ok_or(DeError::InvalidValue { offset })
+    }
+}
+
+impl QuickSer for char {
+    gen_quick_copy!();
+}
+
+impl QuickSer for SystemTime {
+    const SCHEMA_HASH: u64 = schema_hash("SystemTime");
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        let (after_epoch, duration) = match self.duration_since(UNIX_EPOCH) {
+            Ok(duration) => (true, duration),
+            Err(err) => (false, err.duration()),
+        };
+        after_epoch.ser(buffer);
+        duration.as_secs().ser(buffer);
+        duration.subsec_nanos().ser(buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        false.ser_len() + 0u64.ser_len() + 0u32.ser_len()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        let offset = source.offset();
+        let after_epoch = bool::try_de_ser_from(source)?;
+        let secs = u64::try_de_ser_from(source)?;
+        let nanos = u32::try_de_ser_from(source)?;
+        if nanos >= 1_000_000_000 {
+            return Err(DeError::InvalidValue { offset });
+        }
+        let duration = Duration::new(secs, nanos);
+        let time = if after_epoch {
+            UNIX_EPOCH.checked_add(duration)
+        } else {
+            UNIX_EPOCH.checked_sub(duration)
+        };
+        time.ok_or(DeError::InvalidValue { offset })
+    }
+}
+
 macro_rules! impl_traits_for_tuples {
-    ($(($($type:ident),*)),*) => {
+    ($(($($type:ident $index:tt),*)),*) => {
         $(
-            impl<$($type: RealQuickSer),*> RealQuickSer for ($($type),*) {}
+            /// Tuples of quick elements without padding are copied as they are in memory, 
+            /// which reorders the fields, so this does not hold in portable mode.
+            impl<$($type: QuickSer),*> QuickSer for ($($type,)*) {
+                const QUICK: bool = !PORTABLE $( && $type::QUICK )*
+                    && std::mem::size_of::<Self>() == 0 $( + std::mem::size_of::<$type>() )*;
+                const SCHEMA_HASH: u64 = {
+                    let hash = schema_hash(stringify!(($($type),*)));
+                    $(
+                        let hash = schema_combine(hash, $type::SCHEMA_HASH);
+                    )*
+                    if Self::QUICK {
+                        schema_layout(hash, std::mem::size_of::<Self>(), &[$(std::mem::offset_of!(Self, $index)),*])
+                    } else {
+                        hash
+                    }
+                };
+
+                fn ser<S: QuickSink>(&self, buffer: &mut S) {
+                    if Self::QUICK {
+                        return buffer.write(unsafe {
+                            std::slice::from_raw_parts(
+                                self as *const Self as *const u8, 
+                                std::mem::size_of::<Self>()
+                            )
+                        });
+                    }
+                    $(
+                        self.$index.ser(buffer);
+                    )*
+                }
+
+                fn ser_len(&self) -> usize {
+                    if Self::QUICK {
+                        return std::mem::size_of::<Self>();
+                    }
+                    0 $( + self.$index.ser_len() )*
+                }
+
+                fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+                    if Self::QUICK {
+                        let offset = source.offset();
+                        let bytes = source.take(std::mem::size_of::<Self>())?;
+                        if !Self::is_valid_quick(bytes) {
+                            return Err(DeError::InvalidValue { offset });
+                        }
+                        return Ok(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const Self) });
+                    }
+                    Ok(($(
+                        $type::try_de_ser_from(source)?,
+                    )*))
+                }
+
+                fn is_valid_quick(bytes: &[u8]) -> bool {
+                    true $(
+                        && $type::is_valid_quick(
+                            &bytes[std::mem::offset_of!(Self, $index)..][..std::mem::size_of::<$type>()]
+                        )
+                    )*
+                }
+            }
+
+            impl<$($type: RealQuickSer),*> RealQuickSer for ($($type,)*) {
+                const LAYOUT_CHECK: () = {
+                    $( let () = $type::LAYOUT_CHECK; )*
+                    assert!(
+                        std::mem::size_of::<Self>() == 0 $( + std::mem::size_of::<$type>() )*,
+                        "tuple contains padding bytes, use a struct with explicit padding instead"
+                    );
+                };
+                const QUICK_SCHEMA_HASH: u64 = {
+                    let hash = schema_hash(stringify!(($($type),*)));
+                    $(
+                        let hash = schema_combine(hash, $type::QUICK_SCHEMA_HASH);
+                    )*
+                    if !PORTABLE {
+                        schema_layout(hash, std::mem::size_of::<Self>(), &[$(std::mem::offset_of!(Self, $index)),*])
+                    } else {
+                        hash
+                    }
+                };
+
+                fn is_valid(bytes: &[u8]) -> bool {
+                    true $(
+                        && $type::is_valid(
+                            &bytes[std::mem::offset_of!(Self, $index)..][..std::mem::size_of::<$type>()]
+                        )
+                    )*
+                }
+
+                fn ser_portable<S: QuickSink>(&self, buffer: &mut S) {
+                    $(
+                        self.$index.ser_portable(buffer);
+                    )*
+                }
+
+                fn try_de_ser_portable<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+                    Ok(($(
+                        $type::try_de_ser_portable(source)?,
+                    )*))
+                }
+            }
         )*
     }
 }
 
-// seems enough to me
 impl_traits_for_tuples!(
-    (A, B),
-    (A, B, C),
-    (A, B, C, D),
-    (A, B, C, D, E),
-    (A, B, C, D, E, F),
-    (A, B, C, D, E, F, G),
-    (A, B, C, D, E, F, G, H)
-);
\ No newline at end of file
+    (A 0),
+    (A 0, B 1),
+    (A 0, B 1, C 2),
+    (A 0, B 1, C 2, D 3),
+    (A 0, B 1, C 2, D 3, E 4),
+    (A 0, B 1, C 2, D 3, E 4, F 5),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13, O 14),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13, O 14, P 15)
+);
+
+impl RealQuickSer for () {
+    const PORTABLE_LAYOUT: bool = true;
+    const QUICK_SCHEMA_HASH: u64 = schema_hash("()");
+
+    fn ser_portable<S: QuickSink>(&self, _buffer: &mut S) {}
+
+    fn try_de_ser_portable<S: QuickSource>(_source: &mut S) -> Result<Self, DeError> {
+        Ok(())
+    }
+}
+
+impl QuickSer for () {
+    gen_quick_copy!();
+}
@@ -0,0 +1,128 @@
+//! Limits for decoding untrusted input.
+//!
+//! Wrapping a [`QuickSource`] in a [`LimitedSource`] bounds how much a payload can
+//! make the decoder allocate and how deeply it can nest collections, the declared
+//! lengths are checked before anything is allocated for them.
+//!
+//! Besides [`try_de_ser`] and [`try_de_ser_borrowed`], files, frames and readers 
+//! are decoded under limits by [`crate::fs::load_limited`], [`crate::frame::read_limited`]
+//! and [`crate::stream::read_limited`].
+
+use crate::{BorrowSource, DeError, QuickDe, QuickSer, QuickSource, SliceSource};
+
+/// Bounds enforced by [`LimitedSource`], the default enforces nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Elements of a single collection, or bytes of a single string.
+    pub max_len: usize,
+    /// Bytes allocated by all collections and strings together.
+    pub max_alloc: usize,
+    /// Collections and boxes nested in each other.
+    pub max_depth: usize,
+    /// Rejects maps and sets containing the same key twice.
+    pub strict: bool,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_len: usize::MAX,
+            max_alloc: usize::MAX,
+            max_depth: usize::MAX,
+            strict: false,
+        }
+    }
+}
+
+/// Same as [`QuickSer::try_de_ser`] but enforces `limits`.
+pub fn try_de_ser<T: QuickSer>(progress: &mut usize, buffer: &[u8], limits: Limits) -> Result<T, DeError> {
+    let mut source = LimitedSource::new(SliceSource::new(buffer, *progress), limits);
+    let result = T::try_de_ser_from(&mut source);
+    *progress = source.offset();
+    result
+}
+
+/// Same as [`QuickDe::try_de_ser_borrowed`] but enforces `limits`.
+pub fn try_de_ser_borrowed<'a, T: QuickDe<'a>>(
+    progress: &mut usize, 
+    buffer: &'a [u8], 
+    limits: Limits,
+) -> Result<T, DeError> {
+    let mut source = LimitedSource::new(SliceSource::new(buffer, *progress), limits);
+    let result = T::try_de_ser_borrowed_from(&mut source);
+    *progress = source.offset();
+    result
+}
+
+pub struct LimitedSource<S> {
+    source: S,
+    limits: Limits,
+    allocated: usize,
+    depth: usize,
+}
+
+impl<S: QuickSource> LimitedSource<S> {
+    pub fn new(source: S, limits: Limits) -> Self {
+        Self { source, limits, allocated: 0, depth: 0 }
+    }
+
+    /// Bytes counted against [`Limits::max_alloc`] so far.
+    pub fn allocated(&self) -> usize {
+        self.allocated
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+unsafe impl<S: QuickSource> QuickSource for LimitedSource<S> {
+    fn offset(&self) -> usize {
+        self.source.offset()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], DeError> {
+        self.source.take(len)
+    }
+
+    fn trusted(&self) -> bool {
+        self.source.trusted()
+    }
+
+    fn check_len(&mut self, len: usize, size: usize) -> Result<(), DeError> {
+        let offset = self.offset();
+        let allocated = Some(len)
+            .filter(|&len| len <= self.limits.max_len)
+            .and_then(|len| len.checked_mul(size))
+            .and_then(|bytes| bytes.checked_add(self.allocated))
+            .filter(|&allocated| allocated <= self.limits.max_alloc)
+            .ok_or(DeError::LimitExceeded { offset })?;
+        self.source.check_len(len, size)?;
+        self.allocated = allocated;
+        Ok(())
+    }
+
+    fn enter(&mut self) -> Result<(), DeError> {
+        if self.depth >= self.limits.max_depth {
+            return Err(DeError::LimitExceeded { offset: self.offset() });
+        }
+        self.source.enter()?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+        self.source.leave();
+    }
+
+    fn strict(&self) -> bool {
+        self.limits.strict || self.source.strict()
+    }
+}
+
+unsafe impl<'a, S: BorrowSource<'a>> BorrowSource<'a> for LimitedSource<S> {
+    fn take_borrowed(&mut self, len: usize) -> Result<&'a [u8], DeError> {
+        self.source.take_borrowed(len)
+    }
+}
@@ -0,0 +1,110 @@
+//! [`QuickSink`] implementations that do not allocate.
+
+use std::fmt;
+
+use crate::QuickSink;
+
+/// Returned when the serialized value does not fit into a fixed size sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SinkOverflow {
+    pub needed: usize,
+    pub capacity: usize,
+}
+
+impl fmt::Display for SinkOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes do not fit into a sink of {} bytes", self.needed, self.capacity)
+    }
+}
+
+impl std::error::Error for SinkOverflow {}
+
+/// Writes into a borrowed slice. Once a write does not fit, nothing else
+/// is written and [`SliceSink::finish`] reports the overflow.
+pub struct SliceSink<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+    needed: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0, needed: 0 }
+    }
+
+    /// Returns the written prefix of the slice.
+    pub fn finish(self) -> Result<&'a mut [u8], SinkOverflow> {
+        if self.needed > self.buffer.len() {
+            return Err(SinkOverflow { needed: self.needed, capacity: self.buffer.len() });
+        }
+        Ok(&mut self.buffer[..self.len])
+    }
+}
+
+impl QuickSink for SliceSink<'_> {
+    fn write(&mut self, bytes: &[u8]) {
+        write_bounded(self.buffer, &mut self.len, &mut self.needed, bytes);
+    }
+}
+
+/// Stack allocated sink of `N` bytes, behaves like [`SliceSink`].
+pub struct ArraySink<const N: usize> {
+    buffer: [u8; N],
+    len: usize,
+    needed: usize,
+}
+
+impl<const N: usize> ArraySink<N> {
+    pub fn new() -> Self {
+        Self { buffer: [0; N], len: 0, needed: 0 }
+    }
+
+    pub fn finish(&self) -> Result<&[u8], SinkOverflow> {
+        if self.needed > N {
+            return Err(SinkOverflow { needed: self.needed, capacity: N });
+        }
+        Ok(&self.buffer[..self.len])
+    }
+}
+
+impl<const N: usize> Default for ArraySink<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> QuickSink for ArraySink<N> {
+    fn write(&mut self, bytes: &[u8]) {
+        write_bounded(&mut self.buffer, &mut self.len, &mut self.needed, bytes);
+    }
+}
+
+/// Discards the bytes and only counts them.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CountingSink {
+    count: usize,
+}
+
+impl CountingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl QuickSink for CountingSink {
+    fn write(&mut self, bytes: &[u8]) {
+        self.count += bytes.len();
+    }
+}
+
+fn write_bounded(buffer: &mut [u8], len: &mut usize, needed: &mut usize, bytes: &[u8]) {
+    *needed = needed.saturating_add(bytes.len());
+    if *needed <= buffer.len() {
+        buffer[*len..*needed].copy_from_slice(bytes);
+        *len = *needed;
+    }
+}
@@ -0,0 +1,90 @@
+//! Deterministic encoding of hash based collections.
+//!
+//! `HashMap` and `HashSet` are written in iteration order, which changes between
+//! runs. Wrapping them in [`Sorted`], or marking the field `#[quick_ser(sorted)]`,
+//! writes the entries ordered by key instead. The encoding itself is the same,
+//! so sorted and unsorted payloads decode interchangeably and share the schema hash.
+//!
+//! Only the outermost collection is ordered, keys and values are still written
+//! with [`QuickSer::ser`]. A nested hash collection, as in
+//! `HashMap<String, HashSet<u32>>`, has to be wrapped in [`Sorted`] as well
+//! (`HashMap<String, Sorted<HashSet<u32>>>`) for the whole encoding to be deterministic.
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{BuildHasher, Hash},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{write_len, DeError, QuickSer, QuickSink, QuickSource};
+
+pub trait SortedSer: QuickSer {
+    /// Same as [`QuickSer::ser`] but writes the entries ordered by key.
+    fn ser_sorted<S: QuickSink>(&self, buffer: &mut S);
+}
+
+impl<K, V, H> SortedSer for HashMap<K, V, H>
+where
+    K: QuickSer + Eq + Hash + Ord,
+    V: QuickSer,
+    H: BuildHasher + Default,
+{
+    fn ser_sorted<S: QuickSink>(&self, buffer: &mut S) {
+        let mut entries = self.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        write_len(entries.len(), buffer);
+        for (k, v) in entries {
+            k.ser(buffer);
+            v.ser(buffer);
+        }
+    }
+}
+
+impl<T: QuickSer + Eq + Hash + Ord, H: BuildHasher + Default> SortedSer for HashSet<T, H> {
+    fn ser_sorted<S: QuickSink>(&self, buffer: &mut S) {
+        let mut items = self.iter().collect::<Vec<_>>();
+        items.sort_unstable();
+        write_len(items.len(), buffer);
+        for item in items {
+            item.ser(buffer);
+        }
+    }
+}
+
+/// Used by `#[quick_ser(sorted)]` fields, which order only the outermost collection.
+pub fn ser<T: SortedSer, S: QuickSink>(value: &T, buffer: &mut S) {
+    value.ser_sorted(buffer);
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sorted<T>(pub T);
+
+impl<T> Deref for Sorted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Sorted<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: SortedSer> QuickSer for Sorted<T> {
+    const SCHEMA_HASH: u64 = T::SCHEMA_HASH;
+
+    fn ser<S: QuickSink>(&self, buffer: &mut S) {
+        self.0.ser_sorted(buffer);
+    }
+
+    fn ser_len(&self) -> usize {
+        self.0.ser_len()
+    }
+
+    fn try_de_ser_from<S: QuickSource>(source: &mut S) -> Result<Self, DeError> {
+        T::try_de_ser_from(source).map(Sorted)
+    }
+}
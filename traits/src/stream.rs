@@ -0,0 +1,155 @@
+//! Streaming [`QuickSer`] encoding into any [`Write`] and decoding from any [`Read`],
+//! without staging the whole payload in memory.
+
+use std::io::{self, Read, Write};
+
+use crate::{limits::{LimitedSource, Limits}, DeError, QuickSer, QuickSink, QuickSource};
+
+const CHUNK: usize = 8 * 1024;
+
+pub fn write<T: QuickSer>(value: &T, writer: impl Write) -> io::Result<()> {
+    let mut sink = WriteSink::new(writer);
+    value.ser(&mut sink);
+    sink.finish().map(drop)
+}
+
+/// Decodes a single value, bytes read past its end are lost, use [`ReadSource`]
+/// to decode several values from one reader.
+pub fn read<T: QuickSer>(reader: impl Read) -> io::Result<T> {
+    ReadSource::new(reader).decode()
+}
+
+/// Same as [`read`] but decodes the value under `limits`.
+pub fn read_limited<T: QuickSer>(reader: impl Read, limits: Limits) -> io::Result<T> {
+    ReadSource::new(reader).decode_limited(limits)
+}
+
+/// Buffering [`QuickSink`] over a writer. The first io error is kept and
+/// reported by [`WriteSink::finish`], everything written after it is dropped.
+pub struct WriteSink<W> {
+    writer: W,
+    buffer: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> WriteSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, buffer: Vec::with_capacity(CHUNK), error: None }
+    }
+
+    /// Flushes buffered bytes, dropping the sink without calling this loses them.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_buffer();
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_through(&mut self, bytes: &[u8]) {
+        if self.error.is_none() {
+            if let Err(err) = self.writer.write_all(bytes) {
+                self.error = Some(err);
+            }
+        }
+    }
+
+    fn flush_buffer(&mut self) {
+        let buffer = std::mem::take(&mut self.buffer);
+        self.write_through(&buffer);
+        self.buffer = buffer;
+        self.buffer.clear();
+    }
+}
+
+impl<W: Write> QuickSink for WriteSink<W> {
+    fn write(&mut self, bytes: &[u8]) {
+        if self.buffer.len() + bytes.len() > CHUNK {
+            self.flush_buffer();
+        }
+        if bytes.len() >= CHUNK {
+            self.write_through(bytes);
+        } else {
+            self.buffer.extend_from_slice(bytes);
+        }
+    }
+}
+
+/// Buffering [`QuickSource`] over a reader. Io errors are kept and reported
+/// by [`ReadSource::decode`] in place of the [`DeError`] they caused.
+pub struct ReadSource<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+    offset: usize,
+    error: Option<io::Error>,
+}
+
+impl<R: Read> ReadSource<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, buffer: Vec::new(), start: 0, end: 0, offset: 0, error: None }
+    }
+
+    /// Decodes the next value, truncated input is reported as
+    /// [`io::ErrorKind::UnexpectedEof`] and malformed input as [`io::ErrorKind::InvalidData`].
+    pub fn decode<T: QuickSer>(&mut self) -> io::Result<T> {
+        T::try_de_ser_from(&mut *self).map_err(|err| self.io_error(err))
+    }
+
+    /// Same as [`ReadSource::decode`] but enforces `limits` on the decoded value.
+    pub fn decode_limited<T: QuickSer>(&mut self, limits: Limits) -> io::Result<T> {
+        T::try_de_ser_from(&mut LimitedSource::new(&mut *self, limits)).map_err(|err| self.io_error(err))
+    }
+
+    fn io_error(&mut self, err: DeError) -> io::Error {
+        match self.error.take() {
+            Some(err) => err,
+            None => match err {
+                DeError::UnexpectedEnd { .. } => io::Error::new(io::ErrorKind::UnexpectedEof, err),
+                _ => io::Error::new(io::ErrorKind::InvalidData, err),
+            },
+        }
+    }
+
+    fn fill(&mut self, len: usize) -> Result<(), DeError> {
+        self.buffer.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+        while self.end < len {
+            if self.end == self.buffer.len() {
+                // grow gradually so that a corrupted length does not allocate
+                // more than the reader can actually provide
+                let grow = (len - self.end).clamp(CHUNK, 1 << 20);
+                self.buffer.resize(self.end + grow, 0);
+            }
+            match self.reader.read(&mut self.buffer[self.end..]) {
+                Ok(0) => return Err(DeError::UnexpectedEnd { offset: self.offset }),
+                Ok(read) => self.end += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+                Err(err) => {
+                    self.error = Some(err);
+                    return Err(DeError::UnexpectedEnd { offset: self.offset });
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+unsafe impl<R: Read> QuickSource for ReadSource<R> {
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], DeError> {
+        if self.end - self.start < len {
+            self.fill(len)?;
+        }
+        let bytes = &self.buffer[self.start..self.start + len];
+        self.start += len;
+        self.offset += len;
+        Ok(bytes)
+    }
+}